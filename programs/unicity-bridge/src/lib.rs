@@ -1,19 +1,159 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
 use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked};
 
 declare_id!("9q5thPnZG7FKKNr61wceXdfuy2QRLYky8RTJonh2YzyB");
 
+/// Length of the rolling window enforced by `BridgeState::daily_limit`.
+pub const DAILY_WINDOW_SECS: i64 = 86_400;
+
+/// Length of a Unicity address: the hex encoding of a SHA-256 public key hash.
+/// Default for both `BridgeState::recipient_min_len` and `recipient_max_len`.
+pub const UNICITY_ADDRESS_LEN: usize = 64;
+
+/// Hard cap on a Unicity recipient, whatever `set_recipient_rules` allows. It
+/// bounds `BridgeState::recipient_max_len`, the check in `validate_unicity_address`
+/// and the `max_len` of any recipient string stored in an account, which keeps
+/// every event carrying a recipient within a known size. Like every recipient
+/// length in the bridge it counts UTF-8 bytes, not characters.
+pub const MAX_RECIPIENT_LEN: usize = 128;
+
+/// Maximum size of the validator set used by `release_sol_multisig`.
+pub const MAX_VALIDATORS: usize = 10;
+
+/// Denominator for basis-point fee rates.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Upper bound for `BridgeState::fee_bps` (10%).
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Upper bound for `BridgeState::relayer_reward_bps` (1%).
+pub const MAX_RELAYER_REWARD_BPS: u16 = 100;
+
+/// Schema version stamped into `BridgeInitialized`, `TokenLocked` and
+/// `EmergencyWithdrawal` as their first field, so indexers can branch on the
+/// layout. Bump it whenever one of those layouts or the lock_id derivation changes.
+pub const EVENT_VERSION: u8 = 16;
+
+/// Decimals of a lamport amount, the unit every SOL lock is booked in.
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Upper bound for `BridgeState::unicity_decimals`.
+pub const MAX_UNICITY_DECIMALS: u8 = 18;
+
+/// Bytes left in `BridgeState::reserved`.
+pub const STATE_RESERVED_LEN: usize = 87;
+
+/// Maximum number of volume-based fee tiers, see `set_fee_tiers`.
+pub const MAX_FEE_TIERS: usize = 3;
+
+/// Maximum number of `BridgeState::withdraw_splits` recipients.
+pub const MAX_WITHDRAW_SPLITS: usize = 4;
+
+/// Maximum number of entries accepted by `lock_sol_batch`.
+pub const MAX_BATCH_SIZE: usize = 10;
+
+/// `BridgeState::emit_flags` bit for `TokenLocked` and `SplTokenLocked`. Relayers
+/// mint on Unicity from these, so `set_emit_flags` always keeps it set.
+pub const EMIT_LOCK: u8 = 1 << 0;
+/// `emit_flags` bit for `TokenReleased`, `SplTokenReleased` and `ReleaseProcessed`.
+pub const EMIT_RELEASE: u8 = 1 << 1;
+/// `emit_flags` bit for `EmergencyWithdrawal`.
+pub const EMIT_WITHDRAWAL: u8 = 1 << 2;
+/// `emit_flags` bit for `HealthOk`.
+pub const EMIT_HEALTH: u8 = 1 << 3;
+/// Every defined `emit_flags` bit.
+pub const EMIT_ALL: u8 = EMIT_LOCK | EMIT_RELEASE | EMIT_WITHDRAWAL | EMIT_HEALTH;
+
 #[program]
 pub mod unicity_bridge {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, admin: Pubkey) -> Result<()> {
+    /// Initializes the bridge. `timelock_secs` is the delay between scheduling and
+    /// executing an emergency withdraw and cannot be changed afterwards.
+    /// `cluster_tag` identifies this deployment in lock and release events.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        admin: Pubkey,
+        timelock_secs: i64,
+        cluster_tag: u8,
+    ) -> Result<()> {
         let bridge_state = &mut ctx.accounts.bridge_state;
+        // `init_if_needed` lets a repeated call reach this check instead of failing
+        // inside the system program with an opaque "already in use" error.
+        require!(bridge_state.admin == Pubkey::default(), BridgeError::AlreadyInitialized);
+        require!(timelock_secs >= 0, BridgeError::InvalidTimelock);
         bridge_state.admin = admin;
         bridge_state.total_locked = 0;
         bridge_state.nonce = 0;
+        bridge_state.paused = false;
+        bridge_state.pending_admin = None;
+        bridge_state.min_lock_amount = 0;
+        bridge_state.max_lock_amount = 0;
+        bridge_state.daily_limit = 0;
+        bridge_state.daily_locked = 0;
+        bridge_state.daily_window_start = 0;
+        bridge_state.fee_bps = 0;
+        bridge_state.fee_collector = admin;
+        bridge_state.relayer_pubkey = Pubkey::default();
+        bridge_state.validators = Vec::new();
+        bridge_state.threshold = 0;
+        bridge_state.bridge_bump = ctx.bumps.bridge_state;
+        bridge_state.escrow_bump = Pubkey::find_program_address(&[b"escrow"], ctx.program_id).1;
+        bridge_state.last_lock_ts = 0;
+        bridge_state.refund_delay = 0;
+        bridge_state.total_released = 0;
+        bridge_state.last_sequence_hash = [0u8; 32];
+        bridge_state.min_interval_secs = 0;
+        bridge_state.recipient_min_len = UNICITY_ADDRESS_LEN as u16;
+        bridge_state.recipient_max_len = UNICITY_ADDRESS_LEN as u16;
+        bridge_state.timelock_secs = timelock_secs;
+        bridge_state.emergency_eta = 0;
+        bridge_state.hot_locked = 0;
+        bridge_state.cold_locked = 0;
+        bridge_state.fee_tiers = Vec::new();
+        bridge_state.release_nonce = 0;
+        bridge_state.next_release_to_process = 0;
+        bridge_state.emit_flags = EMIT_ALL;
+        bridge_state.tvl_cap = 0;
+        bridge_state.guardian = Pubkey::default();
+        bridge_state.cancel_window_secs = 0;
+        bridge_state.unicity_decimals = SOL_DECIMALS;
+        bridge_state.relayer_reward_bps = 0;
+        bridge_state.locks_paused = false;
+        bridge_state.releases_paused = false;
+        bridge_state.current_epoch = 0;
+        bridge_state.epoch_root = [0u8; 32];
+        bridge_state.epoch_lock_count = 0;
+        bridge_state.vault_program = Pubkey::default();
+        bridge_state.cluster_tag = cluster_tag;
+        bridge_state.killed = false;
+        bridge_state.checksum_required = false;
+        bridge_state.treasury = Pubkey::default();
+        bridge_state.total_fees_collected = 0;
+        bridge_state.dust_threshold = 0;
+        bridge_state.referral_bps = 0;
+        bridge_state.emergency_nonce = 0;
+        bridge_state.emergency_binds_nonce = false;
+        bridge_state.total_dest_gas = 0;
+        bridge_state.next_relayer = Pubkey::default();
+        bridge_state.relayer_rotation_eta = 0;
+        bridge_state.max_locks_per_window = 0;
+        bridge_state.velocity_window_secs = 0;
+        bridge_state.velocity_window_start = 0;
+        bridge_state.velocity_window_locks = 0;
+        bridge_state.lifetime_volume = 0;
+        bridge_state.bindings_required = false;
+        bridge_state.withdraw_splits = [WithdrawSplit::default(); MAX_WITHDRAW_SPLITS];
+        bridge_state.reserved = [0u8; STATE_RESERVED_LEN];
         
         emit!(BridgeInitialized {
+            version: EVENT_VERSION,
             admin,
             timestamp: Clock::get()?.unix_timestamp,
         });
@@ -21,176 +161,7335 @@ pub mod unicity_bridge {
         Ok(())
     }
 
-    pub fn lock_sol(ctx: Context<LockSol>, amount: u64, unicity_recipient: String) -> Result<()> {
+    /// Returns the full bridge state via return data, for use with simulated (`view`) calls.
+    pub fn get_state(ctx: Context<GetState>) -> Result<BridgeState> {
+        Ok((*ctx.accounts.bridge_state).clone())
+    }
+
+    /// Asserts the bridge's accounting invariants and emits `HealthOk`, failing
+    /// with the error of the first violated one. Permissionless, so monitoring
+    /// can call it as a heartbeat.
+    pub fn check_invariants(ctx: Context<CheckInvariants>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let rent_minimum = Rent::get()?.minimum_balance(escrow.data_len());
+        let net_locked = ctx.accounts.bridge_state.check_invariants(
+            escrow.lamports(),
+            ctx.accounts.cold_escrow.lamports(),
+            rent_minimum,
+        )?;
+
+        if ctx.accounts.bridge_state.emits(EMIT_HEALTH) {
+            emit!(HealthOk {
+                net_locked,
+                lifetime_volume: ctx.accounts.bridge_state.lifetime_volume,
+                escrow_balance: escrow.lamports(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Locks `amount` lamports for `unicity_recipient` and returns the lock_id and
+    /// the remaining daily headroom via return data, so clients need not parse
+    /// them from the `TokenLocked` event. The lock is refused once `deadline` has
+    /// passed; 0 means no deadline. `dest_gas` more lamports move to the escrow as
+    /// prepaid Unicity minting gas, booked in `total_dest_gas` rather than as
+    /// locked funds.
+    ///
+    /// `TokenLocked` is emitted through a self-CPI (`emit_cpi!`) rather than the
+    /// program log, so it survives log truncation; indexers read it from the
+    /// transaction's inner instructions. The log still gets a `lock_id <base58>`
+    /// line for searching by hand.
+    pub fn lock_sol(
+        ctx: Context<LockSol>,
+        amount: u64,
+        unicity_recipient: String,
+        deadline: i64,
+        dest_gas: u64,
+    ) -> Result<LockOutcome> {
+        let mut accounts = ctx.accounts.lock_accounts();
+        accounts.dest_gas = dest_gas;
+        let event = execute_lock(accounts, amount, unicity_recipient, deadline, [0u8; 32])?;
+        let outcome = LockOutcome::of(&event);
+        emit_cpi!(event);
+
+        Ok(outcome)
+    }
+
+    /// `lock_sol` with a separate `fee_payer` covering transaction fees and the
+    /// `UserStats` rent, so a wallet provider can sponsor the lock. The locked SOL
+    /// and the bridge fee still come from `user` alone.
+    pub fn lock_sol_sponsored(
+        ctx: Context<LockSolSponsored>,
+        amount: u64,
+        unicity_recipient: String,
+        deadline: i64,
+    ) -> Result<LockOutcome> {
+        let accounts = ctx.accounts.lock_accounts();
+        let event = execute_lock(accounts, amount, unicity_recipient, deadline, [0u8; 32])?;
+        let outcome = LockOutcome::of(&event);
+        emit_cpi!(event);
+
+        Ok(outcome)
+    }
+
+    /// `lock_sol` with an opaque `memo` the integrator can use to match the lock
+    /// to its own order id. The bridge only hashes it into the lock_id and emits it.
+    pub fn lock_sol_with_memo(
+        ctx: Context<LockSol>,
+        amount: u64,
+        unicity_recipient: String,
+        deadline: i64,
+        memo: [u8; 32],
+    ) -> Result<LockOutcome> {
+        let accounts = ctx.accounts.lock_accounts();
+        let event = execute_lock(accounts, amount, unicity_recipient, deadline, memo)?;
+        let outcome = LockOutcome::of(&event);
+        emit_cpi!(event);
+
+        Ok(outcome)
+    }
+
+    /// `lock_sol` from a vault PDA of the registered `vault_program`, for
+    /// programs custodying user funds. The vault must be system-owned and sign:
+    /// a program can only sign for its own PDAs, so the vault program makes this
+    /// call as a CPI with `invoke_signed`. `vault_seeds`, bump included, prove
+    /// that `user` is the vault program's PDA; `TokenLocked` records the program.
+    pub fn lock_sol_from_pda(
+        ctx: Context<LockSol>,
+        amount: u64,
+        unicity_recipient: String,
+        vault_seeds: Vec<Vec<u8>>,
+    ) -> Result<LockOutcome> {
+        let vault_program = ctx.accounts.bridge_state.vault_program;
+        check_vault(&vault_program, &vault_seeds, &ctx.accounts.user.key())?;
+
+        let mut accounts = ctx.accounts.lock_accounts();
+        accounts.vault_program = Some(vault_program);
+        let event = execute_lock(accounts, amount, unicity_recipient, 0, [0u8; 32])?;
+        let outcome = LockOutcome::of(&event);
+        emit_cpi!(event);
+
+        Ok(outcome)
+    }
+
+    /// `lock_sol` made idempotent by a client-chosen `client_key`: the lock opens
+    /// a `LockReceipt` at `[b"lock", user, client_key]`, so a retry with the same
+    /// key fails at init instead of locking twice. The user is part of the seeds,
+    /// so nobody else can claim a key seen in a pending transaction. After an
+    /// ambiguous timeout the client reads the lock_id back from the receipt.
+    pub fn lock_sol_idempotent(
+        ctx: Context<LockSolIdempotent>,
+        amount: u64,
+        unicity_recipient: String,
+        deadline: i64,
+        client_key: [u8; 32],
+    ) -> Result<LockOutcome> {
+        let accounts = ctx.accounts.lock.lock_accounts();
+        let event = execute_lock(accounts, amount, unicity_recipient, deadline, [0u8; 32])?;
+        let outcome = LockOutcome::of(&event);
+
+        let receipt = &mut ctx.accounts.lock_receipt;
+        receipt.client_key = client_key;
+        receipt.lock_id = outcome.lock_id;
+        receipt.user = event.user;
+        receipt.amount = event.amount;
+        receipt.fee = event.fee;
+        receipt.locked_at = event.timestamp;
+
+        // `emit_cpi!` expects the event authority at `ctx.accounts`, so view the
+        // nested `LockSol` as its own context.
+        let ctx = Context::new(ctx.program_id, &mut ctx.accounts.lock, &[], ctx.bumps.lock);
+        emit_cpi!(event);
+
+        Ok(outcome)
+    }
+
+    /// `lock_sol` that also opens a `PendingLock` at `[b"pending", user, receipt_key]`
+    /// recording the lock's metadata, so wallets can list a user's pending bridge
+    /// positions. `close_pending_lock` closes it once the lock is settled.
+    pub fn lock_sol_with_receipt(
+        ctx: Context<LockSolWithReceipt>,
+        amount: u64,
+        unicity_recipient: String,
+        deadline: i64,
+        receipt_key: [u8; 32],
+    ) -> Result<LockOutcome> {
+        let accounts = ctx.accounts.lock.lock_accounts();
+        let event = execute_lock(accounts, amount, unicity_recipient, deadline, [0u8; 32])?;
+        let outcome = LockOutcome::of(&event);
+
+        let pending_lock = &mut ctx.accounts.pending_lock;
+        pending_lock.user = event.user;
+        pending_lock.receipt_key = receipt_key;
+        pending_lock.lock_id = outcome.lock_id;
+        pending_lock.amount = event.amount;
+        pending_lock.fee = event.fee;
+        pending_lock.unicity_recipient = event.unicity_recipient.clone();
+        pending_lock.nonce = event.nonce;
+        pending_lock.locked_at = event.timestamp;
+
+        // See `lock_sol_idempotent`
+        let ctx = Context::new(ctx.program_id, &mut ctx.accounts.lock, &[], ctx.bumps.lock);
+        emit_cpi!(event);
+
+        Ok(outcome)
+    }
+
+    /// Closes a `PendingLock` once its lock has a `ProcessedLock`, i.e. was minted
+    /// on Unicity, refunded or cancelled, returning the rent to its user.
+    /// Permissionless, as the rent can only go to the user.
+    pub fn close_pending_lock(ctx: Context<ClosePendingLock>) -> Result<()> {
+        let pending_lock = &ctx.accounts.pending_lock;
+
+        emit!(PendingLockClosed {
+            lock_id: pending_lock.lock_id,
+            user: pending_lock.user,
+            refunded: ctx.accounts.processed_lock.refunded,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Locks several amounts for different Unicity recipients in one transaction.
+    /// Each entry is checked and booked like a `lock_sol` call and gets its own
+    /// nonce and `TokenLocked` event; the funds move in a single transfer.
+    ///
+    /// The remaining accounts carry each entry's `[b"blocked_rcpt", recipient_key]`
+    /// PDA, in entry order.
+    pub fn lock_sol_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LockSolBatch<'info>>,
+        locks: Vec<LockRequest>,
+    ) -> Result<()> {
+        require!(!locks.is_empty(), BridgeError::InvalidAmount);
+        require!(locks.len() <= MAX_BATCH_SIZE, BridgeError::BatchTooLarge);
+        require!(
+            ctx.remaining_accounts.len() == locks.len(),
+            BridgeError::InvalidBlocklistAccount
+        );
+        for (request, blocked) in locks.iter().zip(ctx.remaining_accounts) {
+            check_recipient_not_blocked(ctx.program_id, blocked, &request.unicity_recipient)?;
+        }
+
+        let mut accounts = ctx.accounts.lock_accounts();
+        accounts.bridge_state.check_locks_open()?;
+        let clock = Clock::get()?;
+        let now = accounts.bridge_state.advance_lock_clock(clock.unix_timestamp)?;
+        // A batch counts as a single lock for the per-user cooldown
+        accounts.user_stats.enforce_cooldown(now, accounts.bridge_state.min_interval_secs)?;
+
+        // Fee tiers are picked from the volume before the batch
+        let user_volume = accounts.user_stats.total_locked;
+        let partner_fee_bps = accounts.partner_config.map(|config| config.fee_bps);
+        let mut prepared = Vec::with_capacity(locks.len());
+        let mut total_net: u64 = 0;
+        let mut total_fee: u64 = 0;
+        for request in locks {
+            let bridge_state = &mut accounts.bridge_state;
+            bridge_state.validate_recipient(&request.unicity_recipient)?;
+            bridge_state.check_recipient_checksum(&request.unicity_recipient)?;
+            bridge_state.check_recipient_binding(None, &request.unicity_recipient)?;
+            let lock = bridge_state.prepare_lock(request.amount, user_volume, partner_fee_bps, now)?;
+            total_net = total_net.checked_add(lock.net)
+                .ok_or(BridgeError::Overflow)?;
+            total_fee = total_fee.checked_add(lock.fee)
+                .ok_or(BridgeError::Overflow)?;
+            prepared.push((lock, request.unicity_recipient));
+        }
+        accounts.bridge_state.check_lock_headroom(total_net)?;
+
+        collect_lock_funds(
+            accounts.user,
+            accounts.escrow,
+            accounts.fee_collector,
+            None,
+            total_net,
+            total_fee,
+        )?;
+
+        let escrow_balance = accounts.escrow.lamports();
+        check_rent_exempt(
+            escrow_balance,
+            Rent::get()?.minimum_balance(accounts.escrow.data_len()),
+        )?;
+        // Bounded by MAX_BATCH_SIZE
+        let size = prepared.len() as u16;
+        let mut events = Vec::with_capacity(prepared.len());
+        for (index, (lock, unicity_recipient)) in (0..size).zip(prepared) {
+            events.push(record_lock(
+                &mut accounts,
+                &lock,
+                unicity_recipient,
+                [0u8; 32],
+                &clock,
+                escrow_balance,
+                BatchPosition { index, size },
+            )?);
+        }
+        accounts.bridge_state.check_solvency(escrow_balance)?;
+
+        for event in events {
+            emit_cpi!(event);
+        }
+
+        Ok(())
+    }
+
+    pub fn lock_token(ctx: Context<LockToken>, amount: u64, unicity_recipient: String) -> Result<()> {
         require!(amount > 0, BridgeError::InvalidAmount);
-        require!(unicity_recipient.len() <= 64, BridgeError::InvalidRecipient);
 
         let bridge_state = &mut ctx.accounts.bridge_state;
+        bridge_state.validate_recipient(&unicity_recipient)?;
+        bridge_state.check_recipient_checksum(&unicity_recipient)?;
+        bridge_state.check_recipient_binding(None, &unicity_recipient)?;
+        bridge_state.check_locks_open()?;
+        let now = bridge_state.advance_lock_clock(Clock::get()?.unix_timestamp)?;
+
         let user = &ctx.accounts.user;
-        let escrow = &ctx.accounts.escrow;
+        let mint = &ctx.accounts.mint;
 
-        // Transfer SOL from user to escrow
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &user.key(),
-            &escrow.key(),
+        // Transfer tokens from user to the program-owned escrow token account
+        token::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: ctx.accounts.escrow_token_account.to_account_info(),
+                    authority: user.to_account_info(),
+                },
+            ),
             amount,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &ix,
-            &[
-                user.to_account_info(),
-                escrow.to_account_info(),
-            ],
+            mint.decimals,
         )?;
 
-        // Update bridge state
-        bridge_state.total_locked = bridge_state.total_locked.checked_add(amount)
+        let asset_stats = &mut ctx.accounts.asset_stats;
+        asset_stats.mint = mint.key();
+        asset_stats.total_locked = asset_stats.total_locked.checked_add(amount)
             .ok_or(BridgeError::Overflow)?;
+
+        // Wrapped SOL shares `total_locked` with native locks. It sits in its token
+        // escrow rather than the hot escrow, so `hot_locked` is left alone.
+        let native = mint.key() == native_mint::ID;
+        if native {
+            bridge_state.total_locked = bridge_state.total_locked.checked_add(amount)
+                .ok_or(BridgeError::Overflow)?;
+            bridge_state.record_volume(amount)?;
+            bridge_state.check_tvl_cap()?;
+        }
+
+        // Token locks share the nonce sequence with native SOL locks
         bridge_state.nonce = bridge_state.nonce.checked_add(1)
             .ok_or(BridgeError::Overflow)?;
+        check_lock_velocity(bridge_state, now)?;
 
-        // Create lock event
-        let mut data = Vec::new();
-        data.extend_from_slice(&user.key().to_bytes());
-        data.extend_from_slice(&bridge_state.nonce.to_le_bytes());
-        data.extend_from_slice(&Clock::get()?.unix_timestamp.to_le_bytes());
-        let lock_id = hash(&data).to_bytes();
+        let lock_id = derive_lock_id(
+            &user.key(),
+            bridge_state.nonce,
+            now,
+            amount,
+            &unicity_recipient,
+            &[0u8; 32],
+            0,
+        );
+        let sequence_hash = bridge_state.chain_lock(&lock_id);
+        bridge_state.fold_epoch(&lock_id)?;
 
-        emit!(TokenLocked {
+        emit!(SplTokenLocked {
             lock_id,
+            mint: mint.key(),
             user: user.key(),
             amount,
             unicity_recipient,
             nonce: bridge_state.nonce,
-            timestamp: Clock::get()?.unix_timestamp,
+            timestamp: now,
+            prev_nonce: bridge_state.nonce - 1,
+            sequence_hash,
+            native,
         });
 
         Ok(())
     }
 
-    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
-        let bridge_state = &ctx.accounts.bridge_state;
+    pub fn release_sol(
+        ctx: Context<ReleaseSol>,
+        amount: u64,
+        recipient: Pubkey,
+        unicity_tx_id: [u8; 32],
+    ) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+        require!(ctx.accounts.recipient.key() == recipient, BridgeError::InvalidRecipient);
+
+        execute_release(
+            &mut ctx.accounts.bridge_state,
+            &mut ctx.accounts.processed_release,
+            &ctx.accounts.escrow,
+            &ctx.accounts.recipient,
+            None,
+            &ctx.accounts.system_program,
+            Release { amount, unicity_tx_id, timestamp },
+        )
+    }
+
+    /// Queues a release the hot escrow cannot cover yet. `unicity_tx_id` is
+    /// reserved right away, so it cannot also be released through another path.
+    pub fn enqueue_release(
+        ctx: Context<EnqueueRelease>,
+        amount: u64,
+        recipient: Pubkey,
+        unicity_tx_id: [u8; 32],
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
         require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        bridge_state.check_releases_open()?;
 
-        let escrow = &ctx.accounts.escrow;
-        let admin = &ctx.accounts.admin;
-        
-        // Transfer all SOL from escrow to admin
-        let escrow_balance = escrow.lamports();
-        **escrow.try_borrow_mut_lamports()? -= escrow_balance;
-        **admin.try_borrow_mut_lamports()? += escrow_balance;
+        let release_nonce = bridge_state.release_nonce;
+        bridge_state.release_nonce = release_nonce.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
 
-        emit!(EmergencyWithdrawal {
-            admin: admin.key(),
-            amount: escrow_balance,
+        // Left with `processed_at == 0` until `process_release` pays it out
+        ctx.accounts.processed_release.unicity_tx_id = unicity_tx_id;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let queued_release = &mut ctx.accounts.queued_release;
+        queued_release.release_nonce = release_nonce;
+        queued_release.unicity_tx_id = unicity_tx_id;
+        queued_release.recipient = recipient;
+        queued_release.amount = amount;
+        queued_release.payer = ctx.accounts.admin.key();
+        queued_release.queued_at = timestamp;
+
+        emit!(ReleaseQueued {
+            release_nonce,
+            unicity_tx_id,
+            recipient,
+            amount,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out the oldest queued release once the hot escrow can cover it and
+    /// closes its queue entry. Anyone can crank the queue: the funds only ever go
+    /// to the recipient recorded at enqueue time.
+    pub fn process_release(ctx: Context<ProcessRelease>) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let queued_release = &ctx.accounts.queued_release;
+        let (release_nonce, unicity_tx_id, amount) = (
+            queued_release.release_nonce,
+            queued_release.unicity_tx_id,
+            queued_release.amount,
+        );
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        bridge_state.next_release_to_process = release_nonce.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
+
+        execute_release(
+            bridge_state,
+            &mut ctx.accounts.processed_release,
+            &ctx.accounts.escrow,
+            &ctx.accounts.recipient,
+            None,
+            &ctx.accounts.system_program,
+            Release { amount, unicity_tx_id, timestamp },
+        )?;
+
+        if ctx.accounts.bridge_state.emits(EMIT_RELEASE) {
+            emit!(ReleaseProcessed {
+                release_nonce,
+                unicity_tx_id,
+                recipient: ctx.accounts.recipient.key(),
+                amount,
+                timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Drops the oldest queued release without paying it, so one entry that can
+    /// never be processed does not hold up the rest of the queue. Also frees its
+    /// `unicity_tx_id` reservation, so the release can be retried another way;
+    /// both rents go back to the entry's payer.
+    pub fn cancel_queued_release(ctx: Context<CancelQueuedRelease>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        let queued_release = &ctx.accounts.queued_release;
+        let release_nonce = queued_release.release_nonce;
+        bridge_state.next_release_to_process = release_nonce.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
+
+        emit!(QueuedReleaseCancelled {
+            release_nonce,
+            unicity_tx_id: queued_release.unicity_tx_id,
+            recipient: queued_release.recipient,
+            amount: queued_release.amount,
+            admin: ctx.accounts.admin.key(),
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + BridgeState::INIT_SPACE,
-        seeds = [b"bridge_state"],
-        bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Releases `amount` of `mint` from its escrow to `recipient`'s token account.
+    /// Shares the `[b"processed", unicity_tx_id]` replay guard with SOL releases.
+    pub fn release_token(
+        ctx: Context<ReleaseToken>,
+        amount: u64,
+        recipient: Pubkey,
+        unicity_tx_id: [u8; 32],
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
 
-#[derive(Accounts)]
-pub struct LockSol<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge_state"],
-        bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump
-    )]
-    /// CHECK: This is safe as it's just an escrow account holding SOL
-    pub escrow: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        bridge_state.check_releases_open()?;
 
-#[derive(Accounts)]
-pub struct EmergencyWithdraw<'info> {
-    #[account(
-        seeds = [b"bridge_state"],
-        bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(
-        mut,
-        seeds = [b"escrow"],
-        bump
-    )]
-    /// CHECK: This is safe as it's just an escrow account holding SOL
-    pub escrow: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let timestamp = Clock::get()?.unix_timestamp;
+        let processed_release = &mut ctx.accounts.processed_release;
+        require!(processed_release.processed_at == 0, BridgeError::ReleaseAlreadyProcessed);
 
-#[account]
-#[derive(InitSpace)]
-pub struct BridgeState {
-    pub admin: Pubkey,
-    pub total_locked: u64,
-    pub nonce: u64,
-}
+        let net_locked = ctx.accounts.asset_stats.record_release(amount)?;
+        if ctx.accounts.mint.key() == native_mint::ID {
+            bridge_state.record_wrapped_release(amount)?;
+        }
 
-#[event]
-pub struct BridgeInitialized {
-    pub admin: Pubkey,
-    pub timestamp: i64,
-}
+        processed_release.unicity_tx_id = unicity_tx_id;
+        processed_release.recipient = recipient;
+        processed_release.amount = amount;
+        processed_release.processed_at = timestamp;
 
-#[event]
-pub struct TokenLocked {
-    pub lock_id: [u8; 32],
-    pub user: Pubkey,
-    pub amount: u64,
-    pub unicity_recipient: String,
-    pub nonce: u64,
-    pub timestamp: i64,
-}
+        // Transfer tokens from the mint's escrow, signed by the bridge_state PDA
+        let mint = &ctx.accounts.mint;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"bridge_state", &[bridge_state.bridge_bump]]];
+        token::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.escrow_token_account.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            mint.decimals,
+        )?;
 
-#[event]
-pub struct EmergencyWithdrawal {
-    pub admin: Pubkey,
-    pub amount: u64,
-    pub timestamp: i64,
-}
+        if bridge_state.emits(EMIT_RELEASE) {
+            emit!(SplTokenReleased {
+                unicity_tx_id,
+                mint: mint.key(),
+                recipient,
+                amount,
+                timestamp,
+                net_locked,
+            });
+        }
 
-#[error_code]
-pub enum BridgeError {
-    #[msg("Invalid amount: must be greater than 0")]
-    InvalidAmount,
-    #[msg("Invalid recipient address")]
-    InvalidRecipient,
-    #[msg("Unauthorized: only admin can perform this action")]
-    Unauthorized,
-    #[msg("Arithmetic overflow")]
-    Overflow,
+        Ok(())
+    }
+
+    /// Releases SOL on the strength of a relayer signature over
+    /// `release_message(amount, recipient, unicity_tx_id)`. The signature must be
+    /// verified by an Ed25519 program instruction placed immediately before this one.
+    pub fn claim_with_signature(
+        ctx: Context<ClaimWithSignature>,
+        amount: u64,
+        recipient: Pubkey,
+        unicity_tx_id: [u8; 32],
+        signature: [u8; 64],
+    ) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let relayers = ctx.accounts.bridge_state.accepted_relayers(timestamp);
+        require!(!relayers.is_empty(), BridgeError::RelayerNotSet);
+        require!(ctx.accounts.recipient.key() == recipient, BridgeError::InvalidRecipient);
+
+        let message = release_message(amount, &recipient, &unicity_tx_id);
+        let instructions = &ctx.accounts.instructions;
+        require!(
+            relayers.iter().any(|relayer| {
+                verify_ed25519_signature(instructions, relayer, &message, &signature).is_ok()
+            }),
+            BridgeError::InvalidSignature
+        );
+
+        execute_release(
+            &mut ctx.accounts.bridge_state,
+            &mut ctx.accounts.processed_release,
+            &ctx.accounts.escrow,
+            &ctx.accounts.recipient,
+            Some(&ctx.accounts.payer),
+            &ctx.accounts.system_program,
+            Release { amount, unicity_tx_id, timestamp },
+        )
+    }
+
+    /// Releases SOL once at least `threshold` distinct validators have signed
+    /// `release_message(amount, recipient, unicity_tx_id)`. Each signature must be
+    /// verified by an Ed25519 program instruction earlier in the same transaction.
+    pub fn release_sol_multisig(
+        ctx: Context<ClaimWithSignature>,
+        amount: u64,
+        recipient: Pubkey,
+        unicity_tx_id: [u8; 32],
+        signatures: Vec<[u8; 64]>,
+    ) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let bridge_state = &ctx.accounts.bridge_state;
+        require!(bridge_state.threshold > 0, BridgeError::ValidatorSetNotConfigured);
+        require!(ctx.accounts.recipient.key() == recipient, BridgeError::InvalidRecipient);
+
+        let message = release_message(amount, &recipient, &unicity_tx_id);
+        let verified = verified_ed25519_signatures(&ctx.accounts.instructions, &message)?;
+
+        let mut signers: Vec<Pubkey> = Vec::with_capacity(signatures.len());
+        for signature in &signatures {
+            let signer = verified
+                .iter()
+                .find(|(_, verified_signature)| verified_signature == signature)
+                .map(|(signer, _)| *signer)
+                .ok_or(BridgeError::InvalidSignature)?;
+            require!(bridge_state.validators.contains(&signer), BridgeError::UnknownValidator);
+            if !signers.contains(&signer) {
+                signers.push(signer);
+            }
+        }
+        require!(signers.len() >= bridge_state.threshold as usize, BridgeError::ThresholdNotMet);
+
+        emit!(ValidatorsSigned {
+            unicity_tx_id,
+            validators: signers,
+            threshold: bridge_state.threshold,
+        });
+
+        execute_release(
+            &mut ctx.accounts.bridge_state,
+            &mut ctx.accounts.processed_release,
+            &ctx.accounts.escrow,
+            &ctx.accounts.recipient,
+            Some(&ctx.accounts.payer),
+            &ctx.accounts.system_program,
+            Release { amount, unicity_tx_id, timestamp },
+        )
+    }
+
+    /// Asks for a lock that was never minted on Unicity to be refunded. The admin
+    /// approves it with `refund_lock` once `refund_delay` has elapsed. `lock` is
+    /// the lock's `TokenLocked` preimage: hashing it with the signer as user must
+    /// give `lock_id`, so only the wallet that locked can ask for its refund.
+    pub fn request_refund(
+        ctx: Context<RequestRefund>,
+        lock_id: [u8; 32],
+        lock: LockPreimage,
+    ) -> Result<()> {
+        require!(
+            lock.lock_id(&ctx.accounts.user.key()) == lock_id,
+            BridgeError::LockIdMismatch
+        );
+        let timestamp = Clock::get()?.unix_timestamp;
+        let refund_request = &mut ctx.accounts.refund_request;
+        refund_request.lock_id = lock_id;
+        refund_request.user = ctx.accounts.user.key();
+        refund_request.requested_at = timestamp;
+
+        emit!(RefundRequested {
+            lock_id,
+            user: ctx.accounts.user.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Refunds `amount` of a requested lock to its user. The admin is trusted to
+    /// have checked `amount` against the lock's `TokenLocked` event. Refunding
+    /// marks the lock processed, so it fails if the relayer already recorded a
+    /// Unicity mint via `mark_lock_processed`, and vice versa.
+    pub fn refund_lock(
+        ctx: Context<RefundLock>,
+        lock_id: [u8; 32],
+        user: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(ctx.accounts.user.key() == user, BridgeError::InvalidRecipient);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let refund_request = &ctx.accounts.refund_request;
+        require!(
+            timestamp >= refund_request.requested_at.saturating_add(bridge_state.refund_delay),
+            BridgeError::RefundNotYetAvailable
+        );
+
+        bridge_state.record_release(amount)?;
+
+        let processed_lock = &mut ctx.accounts.processed_lock;
+        processed_lock.lock_id = lock_id;
+        processed_lock.refunded = true;
+        processed_lock.processed_at = timestamp;
+
+        let escrow = &ctx.accounts.escrow;
+        let user_account = &ctx.accounts.user;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+
+        // Transfer SOL from escrow back to the user
+        payer.pay(&user_account.to_account_info(), amount)?;
+
+        emit!(LockRefunded {
+            lock_id,
+            user,
+            amount,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lets the user abort a `lock_sol_idempotent` lock within `cancel_window_secs`
+    /// of it, before relayers act on it. The net amount goes back to the user; the
+    /// fee is not refunded. Marks the lock processed so it cannot also be refunded
+    /// or relayed, and closes its receipt.
+    pub fn cancel_lock(ctx: Context<CancelLock>, lock_id: [u8; 32]) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        let receipt = &ctx.accounts.lock_receipt;
+        let timestamp = Clock::get()?.unix_timestamp;
+        require!(
+            timestamp < receipt.locked_at.saturating_add(bridge_state.cancel_window_secs),
+            BridgeError::CancelWindowExpired
+        );
+
+        let amount = receipt.amount;
+        bridge_state.record_cancel(amount)?;
+
+        let processed_lock = &mut ctx.accounts.processed_lock;
+        processed_lock.lock_id = lock_id;
+        processed_lock.refunded = true;
+        processed_lock.processed_at = timestamp;
+
+        let escrow = &ctx.accounts.escrow;
+        let user = &ctx.accounts.user;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+        payer.pay(&user.to_account_info(), amount)?;
+
+        emit!(LockCancelled {
+            lock_id,
+            user: user.key(),
+            amount,
+            fee_retained: receipt.fee,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Records that a lock was minted on Unicity, which makes it non-refundable.
+    /// Callable by the admin or the configured relayer.
+    pub fn mark_lock_processed(ctx: Context<MarkLockProcessed>, lock_id: [u8; 32]) -> Result<()> {
+        let bridge_state = &ctx.accounts.bridge_state;
+        let authority = ctx.accounts.authority.key();
+        let timestamp = Clock::get()?.unix_timestamp;
+        require!(
+            authority == bridge_state.admin || bridge_state.is_relayer(&authority, timestamp),
+            BridgeError::Unauthorized
+        );
+
+        let processed_lock = &mut ctx.accounts.processed_lock;
+        processed_lock.lock_id = lock_id;
+        processed_lock.refunded = false;
+        processed_lock.processed_at = timestamp;
+
+        emit!(LockProcessed {
+            lock_id,
+            authority,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Finalizes the current epoch's root into its `[b"epoch", epoch]`
+    /// `EpochCommitment` and starts the next epoch from a zero root. Callable by
+    /// the admin or the relayer, so a cron can roll epochs on a schedule.
+    pub fn roll_epoch(ctx: Context<RollEpoch>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        let authority = ctx.accounts.authority.key();
+        let timestamp = Clock::get()?.unix_timestamp;
+        require!(
+            authority == bridge_state.admin || bridge_state.is_relayer(&authority, timestamp),
+            BridgeError::Unauthorized
+        );
+
+        let commitment = &mut ctx.accounts.epoch_commitment;
+        commitment.epoch = bridge_state.current_epoch;
+        commitment.root = bridge_state.epoch_root;
+        commitment.lock_count = bridge_state.epoch_lock_count;
+        commitment.finalized_at = timestamp;
+        bridge_state.roll_epoch()?;
+
+        emit!(EpochRolled {
+            epoch: commitment.epoch,
+            root: commitment.root,
+            lock_count: commitment.lock_count,
+            authority,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Registers `partner` with a custom `fee_bps` applied to locks that pass its
+    /// `PartnerConfig` and its signature, or updates the rate of an existing partner.
+    pub fn register_partner(
+        ctx: Context<RegisterPartner>,
+        partner: Pubkey,
+        fee_bps: u16,
+    ) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+        require!(fee_bps <= MAX_FEE_BPS, BridgeError::FeeTooHigh);
+
+        let partner_config = &mut ctx.accounts.partner_config;
+        partner_config.partner = partner;
+        partner_config.fee_bps = fee_bps;
+
+        emit!(PartnerRegistered {
+            partner,
+            fee_bps,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Blocks `user` from locking by creating its `[b"denied", user]` PDA.
+    pub fn deny_user(ctx: Context<DenyUser>, user: Pubkey) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let denied_user = &mut ctx.accounts.denied_user;
+        denied_user.user = user;
+        denied_user.denied_at = timestamp;
+
+        emit!(DenylistUpdated {
+            user,
+            denied: true,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Binds `user` to `recipient` through its `[b"binding", user]` PDA, replacing
+    /// any earlier binding. While `bindings_required` is set, `lock_sol` only
+    /// accepts that recipient from `user`.
+    pub fn bind_recipient(
+        ctx: Context<BindRecipient>,
+        user: Pubkey,
+        recipient: String,
+    ) -> Result<()> {
+        let bridge_state = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        bridge_state.validate_recipient(&recipient)?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let recipient_hash = recipient_key(&recipient);
+        let recipient_binding = &mut ctx.accounts.recipient_binding;
+        recipient_binding.user = user;
+        recipient_binding.recipient_hash = recipient_hash;
+        recipient_binding.bound_at = timestamp;
+
+        emit!(RecipientBound {
+            user,
+            recipient_hash,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lifts a denial by closing the user's `[b"denied", user]` PDA.
+    pub fn allow_user(ctx: Context<AllowUser>, user: Pubkey) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+
+        emit!(DenylistUpdated {
+            user,
+            denied: false,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Blocks locks to `unicity_recipient` by creating its `[b"blocked_rcpt",
+    /// recipient_key]` PDA. Both hex cases of the address are covered.
+    pub fn block_recipient(ctx: Context<BlockRecipient>, unicity_recipient: String) -> Result<()> {
+        let bridge_state = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        bridge_state.validate_recipient(&unicity_recipient)?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let recipient_key = recipient_key(&unicity_recipient);
+        let blocked_recipient = &mut ctx.accounts.blocked_recipient;
+        blocked_recipient.recipient_key = recipient_key;
+        blocked_recipient.blocked_at = timestamp;
+
+        emit!(RecipientBlocklistUpdated {
+            unicity_recipient,
+            recipient_key,
+            blocked: true,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lifts a block by closing the recipient's `[b"blocked_rcpt", recipient_key]` PDA.
+    pub fn unblock_recipient(
+        ctx: Context<UnblockRecipient>,
+        unicity_recipient: String,
+    ) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+
+        emit!(RecipientBlocklistUpdated {
+            recipient_key: recipient_key(&unicity_recipient),
+            unicity_recipient,
+            blocked: false,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Starts the emergency withdraw timelock; either withdraw instruction can run
+    /// once `timelock_secs` have passed, giving users a window to exit first.
+    pub fn schedule_emergency_withdraw(ctx: Context<ScheduleEmergencyWithdraw>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let eta = bridge_state.schedule_emergency(timestamp)?;
+
+        emit!(EmergencyWithdrawScheduled {
+            eta,
+            nonce: bridge_state.emergency_nonce,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Chooses whether a scheduled emergency withdraw goes stale once a lock lands
+    /// after it was scheduled. A stale schedule fails with `EmergencyScheduleStale`
+    /// and has to be cancelled and scheduled again, timelock included, so the
+    /// withdraw never runs against a balance the operator has not reviewed.
+    pub fn set_emergency_nonce_binding(
+        ctx: Context<SetEmergencyNonceBinding>,
+        emergency_binds_nonce: bool,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.emergency_binds_nonce = emergency_binds_nonce;
+
+        emit!(EmergencyNonceBindingUpdated {
+            emergency_binds_nonce,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_emergency_withdraw(ctx: Context<CancelEmergencyWithdraw>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(bridge_state.emergency_eta != 0, BridgeError::EmergencyNotScheduled);
+
+        bridge_state.emergency_eta = 0;
+
+        emit!(EmergencyWithdrawCancelled {
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sweeps the escrow above its rent reserve to the admin, or across the
+    /// `withdraw_splits` passed as remaining accounts, once a scheduled
+    /// emergency withdraw is due. The accounting is left untouched, so locks fail
+    /// with `SolvencyViolation` until the escrow is topped back up to `net_locked`.
+    /// `reason_code` is an `EmergencyReason` and is echoed in the event.
+    pub fn execute_emergency_withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyWithdraw<'info>>,
+        reason_code: u8,
+    ) -> Result<()> {
+        EmergencyReason::from_code(reason_code)?;
+
+        check_not_aliased(
+            &ctx.accounts.admin.key(),
+            &ctx.accounts.escrow.key(),
+            &ctx.accounts.bridge_state.key(),
+        )?;
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        let guardian = bridge_state.check_guardian(ctx.accounts.guardian.as_ref().map(|g| g.key()))?;
+        let timestamp = Clock::get()?.unix_timestamp;
+        bridge_state.consume_emergency_schedule(timestamp)?;
+
+        let escrow = &ctx.accounts.escrow;
+        let admin = &ctx.accounts.admin;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+        
+        // Transfer all SOL above the rent-exempt reserve from escrow to admin
+        let withdrawable = withdrawable_balance(escrow)?;
+        require!(withdrawable > 0, BridgeError::NothingToWithdraw);
+        pay_emergency_withdraw(
+            bridge_state,
+            payer,
+            &admin.to_account_info(),
+            ctx.remaining_accounts,
+            withdrawable,
+            timestamp,
+        )?;
+
+        if bridge_state.emits(EMIT_WITHDRAWAL) {
+            emit!(EmergencyWithdrawal {
+                version: EVENT_VERSION,
+                admin: admin.key(),
+                amount: withdrawable,
+                timestamp,
+                guardian,
+                reason_code,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Partial counterpart of `execute_emergency_withdraw`, behind the same timelock.
+    pub fn emergency_withdraw_amount<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyWithdraw<'info>>,
+        amount: u64,
+        reason_code: u8,
+    ) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+        EmergencyReason::from_code(reason_code)?;
+
+        check_not_aliased(
+            &ctx.accounts.admin.key(),
+            &ctx.accounts.escrow.key(),
+            &ctx.accounts.bridge_state.key(),
+        )?;
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        let guardian = bridge_state.check_guardian(ctx.accounts.guardian.as_ref().map(|g| g.key()))?;
+        let timestamp = Clock::get()?.unix_timestamp;
+        bridge_state.consume_emergency_schedule(timestamp)?;
+
+        let escrow = &ctx.accounts.escrow;
+        let admin = &ctx.accounts.admin;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+
+        // Transfer exactly `amount` from escrow to admin, never touching the rent-exempt
+        // reserve. A drained escrow is reported as such rather than as too small.
+        let withdrawable = withdrawable_balance(escrow)?;
+        require!(withdrawable > 0, BridgeError::NothingToWithdraw);
+        require!(amount <= withdrawable, BridgeError::InsufficientEscrow);
+        pay_emergency_withdraw(
+            bridge_state,
+            payer,
+            &admin.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+            timestamp,
+        )?;
+
+        if bridge_state.emits(EMIT_WITHDRAWAL) {
+            emit!(EmergencyWithdrawal {
+                version: EVENT_VERSION,
+                admin: admin.key(),
+                amount,
+                timestamp,
+                guardian,
+                reason_code,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Moves `amount` lamports from the hot escrow to the cold one, or back when
+    /// `to_cold` is false. Moving funds back out of cold storage takes a due
+    /// emergency schedule, so an admin key compromise cannot drain it at once.
+    /// The first move into the cold escrow must cover its rent-exempt minimum.
+    pub fn rebalance_escrow(ctx: Context<RebalanceEscrow>, amount: u64, to_cold: bool) -> Result<()> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        if !to_cold {
+            bridge_state.consume_emergency_schedule(timestamp)?;
+        }
+        bridge_state.rebalance(amount, to_cold)?;
+
+        let escrow = &ctx.accounts.escrow;
+        let cold_escrow = &ctx.accounts.cold_escrow;
+        let system_program = &ctx.accounts.system_program;
+        let payer = EscrowPayer::canonical(escrow, system_program, ctx.program_id)?;
+        if to_cold {
+            payer.pay(cold_escrow, amount)?;
+        } else {
+            let seeds: &[&[u8]] = &[b"escrow_cold", &[ctx.bumps.cold_escrow]];
+            transfer_from_pda(system_program, cold_escrow, escrow, seeds, amount)?;
+        }
+
+        emit!(EscrowRebalanced {
+            amount,
+            to_cold,
+            hot_locked: bridge_state.hot_locked,
+            cold_locked: bridge_state.cold_locked,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sweeps lamports sent to the hot escrow outside of a lock (anything above its
+    /// rent reserve and `hot_escrow_committed`) to the fee collector. The surplus is never
+    /// folded into `total_locked`: nothing was minted on Unicity against it, so
+    /// counting it as locked would overstate the bridge's liability.
+    pub fn reconcile(ctx: Context<Reconcile>) -> Result<()> {
+        let bridge_state = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        let escrow = &ctx.accounts.escrow;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+
+        let rent_minimum = Rent::get()?.minimum_balance(escrow.data_len());
+        let surplus =
+            escrow_surplus(escrow.lamports(), rent_minimum, bridge_state.hot_escrow_committed());
+        require!(surplus > 0, BridgeError::NothingToWithdraw);
+
+        let fee_collector = &ctx.accounts.fee_collector;
+        payer.pay(&fee_collector.to_account_info(), surplus)?;
+
+        emit!(SurplusReconciled {
+            surplus,
+            fee_collector: fee_collector.key(),
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sweeps `amount` lamports, or everything above its rent reserve when
+    /// `amount` is 0, from the `[b"fee_vault"]` PDA to `treasury`. Fees only land
+    /// in the vault once `set_fee` points `fee_collector` at it, and it must
+    /// hold its rent-exempt minimum before the first fee can be paid into it.
+    pub fn collect_fees(ctx: Context<CollectFees>, amount: u64) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(bridge_state.treasury != Pubkey::default(), BridgeError::TreasuryNotSet);
+
+        let fee_vault = &ctx.accounts.fee_vault;
+        let rent_minimum = Rent::get()?.minimum_balance(fee_vault.data_len());
+        let amount = collectable_fees(fee_vault.lamports(), rent_minimum, amount)?;
+        bridge_state.total_fees_collected = bridge_state.total_fees_collected.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+
+        let signer_seeds: &[&[&[u8]]] = &[&[b"fee_vault", &[ctx.bumps.fee_vault]]];
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: fee_vault.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        emit!(FeesCollected {
+            amount,
+            treasury: bridge_state.treasury,
+            total_fees_collected: bridge_state.total_fees_collected,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// `reconcile` restricted to leftovers below `dust_threshold`, so routine
+    /// clean-ups cannot sweep a surplus large enough to deserve a closer look.
+    pub fn sweep_dust(ctx: Context<Reconcile>) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        let bridge_state = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        let escrow = &ctx.accounts.escrow;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+
+        let rent_minimum = Rent::get()?.minimum_balance(escrow.data_len());
+        let surplus =
+            escrow_surplus(escrow.lamports(), rent_minimum, bridge_state.hot_escrow_committed());
+        let dust = sweepable_dust(surplus, bridge_state.dust_threshold)?;
+
+        let fee_collector = &ctx.accounts.fee_collector;
+        payer.pay(&fee_collector.to_account_info(), dust)?;
+
+        emit!(DustSwept {
+            amount: dust,
+            fee_collector: fee_collector.key(),
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the bound below which `sweep_dust` may move an escrow surplus.
+    pub fn set_dust_threshold(ctx: Context<SetDustThreshold>, dust_threshold: u64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.dust_threshold = dust_threshold;
+
+        emit!(DustThresholdUpdated {
+            dust_threshold,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Overwrites `total_locked` after a manual intervention off-chain, recording
+    /// the reason. Only allowed while paused so it cannot race live locks.
+    pub fn adjust_accounting(
+        ctx: Context<AdjustAccounting>,
+        new_total_locked: u64,
+        reason: [u8; 32],
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(bridge_state.paused, BridgeError::BridgeNotPaused);
+
+        let old_total_locked = bridge_state.adjust_total_locked(new_total_locked)?;
+
+        emit!(AccountingAdjusted {
+            old_total_locked,
+            new_total_locked,
+            reason,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the lock nonce to `new_nonce`, so the next lock gets `new_nonce + 1`.
+    /// For recovering from an exhausted nonce or carrying over the nonce of a
+    /// legacy deployment; only allowed while paused.
+    pub fn reset_nonce(ctx: Context<ResetNonce>, new_nonce: u64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(bridge_state.paused, BridgeError::BridgeNotPaused);
+
+        let old_nonce = bridge_state.nonce;
+        bridge_state.nonce = new_nonce;
+
+        emit!(NonceReset {
+            old_nonce,
+            new_nonce,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Registers the program whose vault PDAs may lock through
+    /// `lock_sol_from_pda`; the default key disables it.
+    pub fn set_vault_program(ctx: Context<SetVaultProgram>, vault_program: Pubkey) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.vault_program = vault_program;
+
+        emit!(VaultProgramUpdated {
+            vault_program,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the share of the fee paid to a lock's referrer, at most `fee_bps`.
+    /// Should `fee_bps` be lowered below it later, a referrer never gets more
+    /// than the fee of its lock.
+    pub fn set_referral_bps(ctx: Context<SetReferralBps>, referral_bps: u16) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(referral_bps <= bridge_state.fee_bps, BridgeError::ReferralTooHigh);
+
+        bridge_state.referral_bps = referral_bps;
+
+        emit!(ReferralBpsUpdated {
+            referral_bps,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lets `referrer` be passed as a lock's referrer by creating its
+    /// `[b"referrer", referrer]` PDA.
+    pub fn register_referrer(ctx: Context<RegisterReferrer>, referrer: Pubkey) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let referrer_config = &mut ctx.accounts.referrer_config;
+        referrer_config.referrer = referrer;
+        referrer_config.registered_at = timestamp;
+
+        emit!(ReferrerUpdated {
+            referrer,
+            registered: true,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Stops paying `referrer` by closing its `[b"referrer", referrer]` PDA.
+    pub fn remove_referrer(ctx: Context<RemoveReferrer>, referrer: Pubkey) -> Result<()> {
+        require!(ctx.accounts.admin.key() == ctx.accounts.bridge_state.admin, BridgeError::Unauthorized);
+
+        emit!(ReferrerUpdated {
+            referrer,
+            registered: false,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the account `collect_fees` sweeps the fee vault to.
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            treasury,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the share of signature-verified releases paid to the relayer that
+    /// submits them, capped at `MAX_RELAYER_REWARD_BPS`.
+    pub fn set_relayer_reward(ctx: Context<SetRelayerReward>, relayer_reward_bps: u16) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(
+            relayer_reward_bps <= MAX_RELAYER_REWARD_BPS,
+            BridgeError::RelayerRewardTooHigh
+        );
+
+        bridge_state.relayer_reward_bps = relayer_reward_bps;
+
+        emit!(RelayerRewardUpdated {
+            relayer_reward_bps,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Grows a `bridge_state` created by an older, smaller build to the current
+    /// `8 + BridgeState::INIT_SPACE`, with the admin paying the extra rent. The
+    /// added bytes are zeroed, so fields appended since then read as their
+    /// all-zero default. Never shrinks the account; on a current one it is a no-op.
+    ///
+    /// Anchor's `realloc` constraint needs the account to deserialize first,
+    /// which an outdated one does not, so the resize happens here.
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let bridge_state = ctx.accounts.bridge_state.to_account_info();
+        let admin = state_admin(&bridge_state.try_borrow_data()?)?;
+        require!(ctx.accounts.admin.key() == admin, BridgeError::Unauthorized);
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        let old_space = bridge_state.data_len();
+        let space = 8 + BridgeState::INIT_SPACE;
+        require!(old_space <= space, BridgeError::InvalidStateSize);
+
+        if old_space < space {
+            let top_up = Rent::get()?
+                .minimum_balance(space)
+                .saturating_sub(bridge_state.lamports());
+            if top_up > 0 {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.admin.to_account_info(),
+                            to: bridge_state.clone(),
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+            // Zero-extends the data
+            bridge_state.resize(space)?;
+        }
+
+        emit!(StateMigrated {
+            old_space: old_space as u64,
+            space: space as u64,
+            admin,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Decommissions a paused, fully drained bridge: sweeps whatever is left in the
+    /// escrow to the admin and closes `bridge_state`, returning its rent.
+    pub fn close_bridge(ctx: Context<CloseBridge>) -> Result<()> {
+        let bridge_state = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(bridge_state.paused, BridgeError::BridgeNotPaused);
+        require!(bridge_state.net_locked()? == 0, BridgeError::OutstandingLocks);
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        let escrow = &ctx.accounts.escrow;
+        let admin = &ctx.accounts.admin;
+        let payer = EscrowPayer::canonical(escrow, &ctx.accounts.system_program, ctx.program_id)?;
+
+        // Sweep the escrow, rent reserve included, to admin; `close = admin` then
+        // returns the rent of `bridge_state`.
+        let swept = escrow.lamports();
+        payer.pay(&admin.to_account_info(), swept)?;
+
+        emit!(BridgeClosed {
+            admin: admin.key(),
+            swept,
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.paused = paused;
+        if !paused {
+            // Count afresh, so the first lock after an auto-pause cannot retrip it.
+            bridge_state.velocity_window_locks = 0;
+        }
+
+        emit!(PauseStateChanged {
+            paused,
+            locks_paused: bridge_state.locks_paused,
+            releases_paused: bridge_state.releases_paused,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pauses locks and releases independently, for incidents that only affect
+    /// one direction. `paused` still stops both regardless of these flags.
+    pub fn set_pause_flags(
+        ctx: Context<SetPaused>,
+        locks_paused: bool,
+        releases_paused: bool,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.locks_paused = locks_paused;
+        bridge_state.releases_paused = releases_paused;
+
+        emit!(PauseStateChanged {
+            paused: bridge_state.paused,
+            locks_paused,
+            releases_paused,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Shuts the bridge down for good: every later lock fails with `BridgeKilled`,
+    /// while releases, refunds and cancels of funds already locked go on as before.
+    /// Unlike `set_paused`, nothing can undo it.
+    pub fn kill_bridge(ctx: Context<SetPaused>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(!bridge_state.killed, BridgeError::BridgeKilled);
+
+        bridge_state.killed = true;
+
+        emit!(BridgeKilled {
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.pending_admin = Some(new_admin);
+
+        emit!(AdminTransferProposed {
+            admin: bridge_state.admin,
+            pending_admin: new_admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        let new_admin = ctx.accounts.new_admin.key();
+        require!(bridge_state.pending_admin == Some(new_admin), BridgeError::Unauthorized);
+
+        let previous_admin = bridge_state.admin;
+        bridge_state.admin = new_admin;
+        bridge_state.pending_admin = None;
+
+        emit!(AdminTransferAccepted {
+            previous_admin,
+            admin: new_admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the per-lock amount bounds. A `max` of 0 disables the upper bound.
+    pub fn set_limits(ctx: Context<SetLimits>, min: u64, max: u64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(max == 0 || min <= max, BridgeError::InvalidLimits);
+
+        bridge_state.min_lock_amount = min;
+        bridge_state.max_lock_amount = max;
+
+        emit!(LimitsUpdated {
+            min_lock_amount: min,
+            max_lock_amount: max,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pauses the bridge once more than `max_locks_per_window` locks, SOL or SPL,
+    /// land within `velocity_window_secs`, as a trip for runaway bots or an
+    /// exploit in progress. Either value at 0 disables it. Only `set_paused`
+    /// lifts the resulting pause.
+    pub fn set_lock_velocity_limit(
+        ctx: Context<SetDailyLimit>,
+        max_locks_per_window: u32,
+        velocity_window_secs: i64,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(velocity_window_secs >= 0, BridgeError::InvalidVelocityWindow);
+
+        bridge_state.max_locks_per_window = max_locks_per_window;
+        bridge_state.velocity_window_secs = velocity_window_secs;
+        bridge_state.velocity_window_locks = 0;
+
+        emit!(LockVelocityLimitUpdated {
+            max_locks_per_window,
+            velocity_window_secs,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the cap on SOL locked per `DAILY_WINDOW_SECS`. A limit of 0 disables the cap.
+    pub fn set_daily_limit(ctx: Context<SetDailyLimit>, daily_limit: u64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.daily_limit = daily_limit;
+
+        emit!(DailyLimitUpdated {
+            daily_limit,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_relayer(ctx: Context<SetRelayer>, relayer: Pubkey) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.relayer_pubkey = relayer;
+        bridge_state.next_relayer = Pubkey::default();
+        bridge_state.relayer_rotation_eta = 0;
+
+        emit!(RelayerUpdated {
+            relayer,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Hands the relayer role to `new_relayer` without a gap: for `grace_secs`
+    /// both keys authorize releases, so signatures the old relayer already handed
+    /// out still land, and afterwards only `new_relayer` does. `set_relayer`
+    /// cancels a pending rotation.
+    pub fn rotate_relayer(
+        ctx: Context<SetRelayer>,
+        new_relayer: Pubkey,
+        grace_secs: i64,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(new_relayer != Pubkey::default(), BridgeError::RelayerNotSet);
+        require!(grace_secs >= 0, BridgeError::InvalidGracePeriod);
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        // A rotation whose window already ended is complete; the next one starts
+        // from the relayer it installed.
+        if bridge_state.next_relayer != Pubkey::default()
+            && timestamp >= bridge_state.relayer_rotation_eta
+        {
+            bridge_state.relayer_pubkey = bridge_state.next_relayer;
+        }
+        let rotation_eta = timestamp
+            .checked_add(grace_secs)
+            .ok_or(BridgeError::Overflow)?;
+        bridge_state.next_relayer = new_relayer;
+        bridge_state.relayer_rotation_eta = rotation_eta;
+
+        emit!(RelayerRotationScheduled {
+            relayer: bridge_state.relayer_pubkey,
+            next_relayer: new_relayer,
+            rotation_eta,
+            admin: ctx.accounts.admin.key(),
+            timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the validator set and `threshold` used by `release_sol_multisig`.
+    /// Releases are keyed by `unicity_tx_id`, not by who signed them, so locks
+    /// made under an earlier relayer or validator set stay releasable afterwards.
+    pub fn set_validators(
+        ctx: Context<SetValidators>,
+        validators: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(validators.len() <= MAX_VALIDATORS, BridgeError::TooManyValidators);
+        require!(threshold as usize <= validators.len(), BridgeError::InvalidThreshold);
+        for (i, validator) in validators.iter().enumerate() {
+            require!(!validators[..i].contains(validator), BridgeError::DuplicateValidator);
+        }
+
+        bridge_state.validators = validators.clone();
+        bridge_state.threshold = threshold;
+
+        emit!(ValidatorSetUpdated {
+            validators,
+            threshold,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_refund_delay(ctx: Context<SetRefundDelay>, refund_delay: i64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(refund_delay >= 0, BridgeError::InvalidRefundDelay);
+
+        bridge_state.refund_delay = refund_delay;
+
+        emit!(RefundDelayUpdated {
+            refund_delay,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the minimum number of seconds between two locks from the same wallet;
+    /// 0 disables the cooldown.
+    pub fn set_min_interval(ctx: Context<SetMinInterval>, min_interval_secs: i64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(min_interval_secs >= 0, BridgeError::InvalidMinInterval);
+
+        bridge_state.min_interval_secs = min_interval_secs;
+
+        emit!(MinIntervalUpdated {
+            min_interval_secs,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Makes locks require the `verify_recipient_checksum` casing of their
+    /// recipient, which catches most mistyped addresses.
+    pub fn set_checksum_required(
+        ctx: Context<SetChecksumRequired>,
+        checksum_required: bool,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.checksum_required = checksum_required;
+
+        emit!(ChecksumRequiredUpdated {
+            checksum_required,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Makes `lock_sol` require the user's `bind_recipient` binding and refuses
+    /// every lock path that cannot pass one, for KYC'd deployments.
+    pub fn set_bindings_required(
+        ctx: Context<SetChecksumRequired>,
+        bindings_required: bool,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.bindings_required = bindings_required;
+
+        emit!(BindingsRequiredUpdated {
+            bindings_required,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the accepted Unicity recipient length range in bytes, for network
+    /// versions with a different address encoding.
+    pub fn set_recipient_rules(
+        ctx: Context<SetRecipientRules>,
+        min_len: u16,
+        max_len: u16,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(
+            min_len > 0 && min_len <= max_len && max_len as usize <= MAX_RECIPIENT_LEN,
+            BridgeError::InvalidRecipientRules
+        );
+
+        bridge_state.recipient_min_len = min_len;
+        bridge_state.recipient_max_len = max_len;
+
+        emit!(RecipientRulesUpdated {
+            min_len,
+            max_len,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_fee(ctx: Context<SetFee>, fee_bps: u16, fee_collector: Pubkey) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(fee_bps <= MAX_FEE_BPS, BridgeError::FeeTooHigh);
+
+        bridge_state.fee_bps = fee_bps;
+        bridge_state.fee_collector = fee_collector;
+
+        emit!(FeeUpdated {
+            fee_bps,
+            fee_collector,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the volume-based fee tiers. A tier applies once a wallet's
+    /// lifetime `UserStats::total_locked` reaches its threshold, and a lock pays
+    /// the lowest rate among the base `fee_bps` and every applicable tier.
+    pub fn set_fee_tiers(ctx: Context<SetFeeTiers>, tiers: Vec<FeeTier>) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(tiers.len() <= MAX_FEE_TIERS, BridgeError::TooManyFeeTiers);
+        require!(
+            tiers.iter().all(|tier| tier.fee_bps <= MAX_FEE_BPS),
+            BridgeError::FeeTooHigh
+        );
+
+        bridge_state.fee_tiers = tiers.clone();
+
+        emit!(FeeTiersUpdated {
+            tiers,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Splits future emergency withdraws across `splits`, whose shares must add up
+    /// to `BPS_DENOMINATOR`. The withdraw then takes each split recipient as a
+    /// remaining account, in this order. An empty list pays the admin again.
+    pub fn set_withdraw_splits(
+        ctx: Context<SetFeeTiers>,
+        splits: Vec<WithdrawSplit>,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        validate_withdraw_splits(&splits)?;
+
+        let mut slots = [WithdrawSplit::default(); MAX_WITHDRAW_SPLITS];
+        slots[..splits.len()].copy_from_slice(&splits);
+        bridge_state.withdraw_splits = slots;
+
+        emit!(WithdrawSplitsUpdated {
+            splits,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the guardian that must co-sign emergency withdraws; the default key
+    /// goes back to admin-only withdraws.
+    pub fn set_guardian(ctx: Context<SetGuardian>, guardian: Pubkey) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.guardian = guardian;
+
+        emit!(GuardianUpdated {
+            guardian,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets how long after a lock its user may still `cancel_lock` it.
+    pub fn set_cancel_window(ctx: Context<SetCancelWindow>, cancel_window_secs: i64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(cancel_window_secs >= 0, BridgeError::InvalidCancelWindow);
+
+        bridge_state.cancel_window_secs = cancel_window_secs;
+
+        emit!(CancelWindowUpdated {
+            cancel_window_secs,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the decimals Unicity amounts are expressed in, which scales
+    /// `TokenLocked::unicity_amount` relative to the lamport `amount`.
+    pub fn set_unicity_decimals(
+        ctx: Context<SetUnicityDecimals>,
+        unicity_decimals: u8,
+    ) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(unicity_decimals <= MAX_UNICITY_DECIMALS, BridgeError::InvalidDecimals);
+
+        bridge_state.unicity_decimals = unicity_decimals;
+
+        emit!(UnicityDecimalsUpdated {
+            unicity_decimals,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the `net_locked` ceiling for new locks; 0 removes it. Lowering it
+    /// below the current `net_locked` only blocks locks until releases catch up.
+    pub fn set_tvl_cap(ctx: Context<SetTvlCap>, tvl_cap: u64) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+
+        bridge_state.tvl_cap = tvl_cap;
+
+        emit!(TvlCapUpdated {
+            tvl_cap,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets which optional events are emitted. `EMIT_LOCK` is forced on.
+    pub fn set_emit_flags(ctx: Context<SetEmitFlags>, emit_flags: u8) -> Result<()> {
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.admin.key() == bridge_state.admin, BridgeError::Unauthorized);
+        require!(emit_flags & !EMIT_ALL == 0, BridgeError::InvalidEmitFlags);
+
+        let emit_flags = emit_flags | EMIT_LOCK;
+        bridge_state.emit_flags = emit_flags;
+
+        emit!(EmitFlagsUpdated {
+            emit_flags,
+            admin: ctx.accounts.admin.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+/// Books a release of `amount` against the bridge accounting, marks `unicity_tx_id`
+/// as processed and pays the recipient from the escrow. Callers authorize the release.
+fn execute_release<'info>(
+    bridge_state: &mut BridgeState,
+    processed_release: &mut ProcessedRelease,
+    escrow: &AccountInfo<'info>,
+    recipient: &AccountInfo<'info>,
+    relayer: Option<&Signer<'info>>,
+    system_program: &Program<'info, System>,
+    release: Release,
+) -> Result<()> {
+    let Release { amount, unicity_tx_id, timestamp } = release;
+    require!(amount > 0, BridgeError::InvalidAmount);
+    bridge_state.check_releases_open()?;
+
+    // `init` on the ProcessedRelease PDA already rejects a replayed unicity_tx_id;
+    // this guards against the constraint ever being relaxed.
+    require!(processed_release.processed_at == 0, BridgeError::ReleaseAlreadyProcessed);
+
+    let net_locked = bridge_state.record_release(amount)?;
+    let relayer_reward = match relayer {
+        Some(_) => bridge_state.relayer_reward(amount)?,
+        None => 0,
+    };
+
+    processed_release.unicity_tx_id = unicity_tx_id;
+    processed_release.recipient = recipient.key();
+    processed_release.amount = amount;
+    processed_release.processed_at = timestamp;
+
+    // Transfer SOL from escrow to recipient
+    // The relayer reward comes out of the released amount, so `amount` still
+    // leaves the escrow in total
+    let payer = EscrowPayer::canonical(escrow, system_program, &ID)?;
+    payer.pay(recipient, amount - relayer_reward)?;
+    if let Some(relayer) = relayer.filter(|_| relayer_reward > 0) {
+        payer.pay(&relayer.to_account_info(), relayer_reward)?;
+    }
+
+    if bridge_state.emits(EMIT_RELEASE) {
+        emit!(TokenReleased {
+            unicity_tx_id,
+            recipient: recipient.key(),
+            amount,
+            timestamp,
+            net_locked,
+            relayer: relayer.map(|relayer| relayer.key()),
+            relayer_reward,
+            cluster_tag: bridge_state.cluster_tag,
+        });
+    }
+
+    Ok(())
+}
+
+/// Payload a relayer or validator signs to authorize a release, and the exact
+/// bytes `claim_with_signature` and `release_sol_multisig` verify: `amount` as
+/// 8 little-endian bytes, then the 32 `recipient` key bytes, then the 32 bytes of
+/// `unicity_tx_id`. 72 bytes in all, with no padding or length prefixes.
+pub fn release_message(amount: u64, recipient: &Pubkey, unicity_tx_id: &[u8; 32]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(8 + 32 + 32);
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&recipient.to_bytes());
+    message.extend_from_slice(unicity_tx_id);
+    message
+}
+
+/// Size of the Ed25519 program's per-signature offsets record.
+const ED25519_OFFSETS_LEN: usize = 14;
+
+/// Ed25519 program signature checks as `(signer, signature, message)`.
+type Ed25519Entry<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/// Parses the signatures verified by an Ed25519 program instruction. Only
+/// offsets pointing into the instruction's own data are accepted.
+fn parse_ed25519_instruction(data: &[u8]) -> Result<Vec<Ed25519Entry<'_>>> {
+    // Layout: num_signatures (u8), padding (u8), then one offsets record per signature.
+    let count = *data.first().ok_or(BridgeError::InvalidSignature)? as usize;
+    require!(data.len() >= 2 + count * ED25519_OFFSETS_LEN, BridgeError::InvalidSignature);
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let slice = |offset: usize, len: usize| data.get(offset..offset + len);
+    let this_ix = u16::MAX as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for n in 0..count {
+        let at = 2 + n * ED25519_OFFSETS_LEN;
+        let signature_offset = read_u16(at);
+        let signature_ix = read_u16(at + 2);
+        let pubkey_offset = read_u16(at + 4);
+        let pubkey_ix = read_u16(at + 6);
+        let message_offset = read_u16(at + 8);
+        let message_size = read_u16(at + 10);
+        let message_ix = read_u16(at + 12);
+        require!(
+            signature_ix == this_ix && pubkey_ix == this_ix && message_ix == this_ix,
+            BridgeError::InvalidSignature
+        );
+
+        entries.push((
+            slice(pubkey_offset, 32).ok_or(BridgeError::InvalidSignature)?,
+            slice(signature_offset, 64).ok_or(BridgeError::InvalidSignature)?,
+            slice(message_offset, message_size).ok_or(BridgeError::InvalidSignature)?,
+        ));
+    }
+
+    Ok(entries)
+}
+
+/// Checks that the instruction preceding the current one is an Ed25519 program
+/// instruction verifying `signature` by `signer` over exactly `message`.
+fn verify_ed25519_signature(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, BridgeError::InvalidSignature);
+    let ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+    require!(ix.program_id == ed25519_program::ID, BridgeError::InvalidSignature);
+
+    let entries = parse_ed25519_instruction(&ix.data)?;
+    require!(
+        entries.len() == 1
+            && entries[0] == (signer.as_ref(), &signature[..], message),
+        BridgeError::InvalidSignature
+    );
+
+    Ok(())
+}
+
+/// Collects every `(signer, signature)` over `message` verified by Ed25519 program
+/// instructions that precede the current instruction.
+fn verified_ed25519_signatures(
+    instructions: &AccountInfo,
+    message: &[u8],
+) -> Result<Vec<(Pubkey, [u8; 64])>> {
+    let current_index = load_current_index_checked(instructions)?;
+
+    let mut verified = Vec::new();
+    for index in 0..current_index as usize {
+        let ix = load_instruction_at_checked(index, instructions)?;
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+        for (signer, signature, signed) in parse_ed25519_instruction(&ix.data)? {
+            if signed == message {
+                let signer = Pubkey::try_from(signer).map_err(|_| BridgeError::InvalidSignature)?;
+                let signature = <[u8; 64]>::try_from(signature).map_err(|_| BridgeError::InvalidSignature)?;
+                verified.push((signer, signature));
+            }
+        }
+    }
+
+    Ok(verified)
+}
+
+/// A release being paid out by `execute_release`. `timestamp` is read from the
+/// clock at the top of the calling handler, before any state or lamports move,
+/// so a failed clock read aborts the release before it has touched anything.
+#[derive(Clone, Copy)]
+struct Release {
+    amount: u64,
+    unicity_tx_id: [u8; 32],
+    timestamp: i64,
+}
+
+/// Where a lock sits within the `lock_sol_batch` call that made it.
+#[derive(Clone, Copy)]
+struct BatchPosition {
+    index: u16,
+    size: u16,
+}
+
+impl BatchPosition {
+    /// Position of a lock made on its own.
+    const SINGLE: Self = Self { index: 0, size: 1 };
+}
+
+/// Accounts a SOL lock reads and writes, borrowed from whichever lock context
+/// the instruction came in with.
+struct LockAccounts<'a, 'info> {
+    bridge_state: &'a mut BridgeState,
+    user_stats: &'a mut UserStats,
+    escrow: &'a AccountInfo<'info>,
+    fee_collector: &'a SystemAccount<'info>,
+    /// `None` for batches, whose entries are only counted in their events.
+    recipient_stats: Option<&'a mut RecipientStats>,
+    partner_config: Option<&'a PartnerConfig>,
+    /// Front-end credited with the lock, only ever passed through `LockSol`.
+    referrer: Option<&'a SystemAccount<'info>>,
+    /// `ReferrerConfig` of `referrer`, which must be passed with it.
+    referrer_config: Option<&'a ReferrerConfig>,
+    /// Also only passed through `LockSol`, so other lock paths are refused while
+    /// `bindings_required` is set.
+    recipient_binding: Option<&'a RecipientBinding>,
+    user: &'a Signer<'info>,
+    /// Pays the `UserStats` rent; `user` unless the lock is sponsored.
+    fee_payer: Pubkey,
+    /// Set when `user` is a vault PDA verified by `lock_sol_from_pda`.
+    vault_program: Option<Pubkey>,
+    /// Prepaid Unicity gas moved to the escrow with the lock, set by `lock_sol`.
+    dest_gas: u64,
+}
+
+impl LockAccounts<'_, '_> {
+    /// Part of `lock`'s fee owed to the referrer, 0 without one.
+    fn referral_fee(&self, lock: &LockAmounts) -> Result<u64> {
+        match self.referrer {
+            Some(_) => self.bridge_state.referral_fee(lock),
+            None => Ok(0),
+        }
+    }
+}
+
+impl<'info> LockSol<'info> {
+    fn lock_accounts(&mut self) -> LockAccounts<'_, 'info> {
+        LockAccounts {
+            bridge_state: &mut self.bridge_state,
+            user_stats: &mut self.user_stats,
+            escrow: &self.escrow,
+            fee_collector: &self.fee_collector,
+            recipient_stats: Some(&mut self.recipient_stats),
+            partner_config: self.partner_config.as_deref(),
+            referrer: self.referrer.as_ref(),
+            referrer_config: self.referrer_config.as_deref(),
+            recipient_binding: self.recipient_binding.as_deref(),
+            user: &self.user,
+            fee_payer: self.user.key(),
+            vault_program: None,
+            dest_gas: 0,
+        }
+    }
+}
+
+impl<'info> LockSolSponsored<'info> {
+    fn lock_accounts(&mut self) -> LockAccounts<'_, 'info> {
+        LockAccounts {
+            bridge_state: &mut self.bridge_state,
+            user_stats: &mut self.user_stats,
+            escrow: &self.escrow,
+            fee_collector: &self.fee_collector,
+            recipient_stats: Some(&mut self.recipient_stats),
+            partner_config: self.partner_config.as_deref(),
+            user: &self.user,
+            referrer: None,
+            referrer_config: None,
+            recipient_binding: None,
+            fee_payer: self.fee_payer.key(),
+            vault_program: None,
+            dest_gas: 0,
+        }
+    }
+}
+
+impl<'info> LockSolBatch<'info> {
+    fn lock_accounts(&mut self) -> LockAccounts<'_, 'info> {
+        LockAccounts {
+            bridge_state: &mut self.bridge_state,
+            user_stats: &mut self.user_stats,
+            escrow: &self.escrow,
+            fee_collector: &self.fee_collector,
+            recipient_stats: None,
+            partner_config: self.partner_config.as_deref(),
+            referrer: None,
+            referrer_config: None,
+            recipient_binding: None,
+            user: &self.user,
+            fee_payer: self.user.key(),
+            vault_program: None,
+            dest_gas: 0,
+        }
+    }
+}
+
+/// Checks that `referrer_config`, which only `register_referrer` creates, is the
+/// registration of `referrer`.
+fn check_referrer_registered(
+    referrer: &Pubkey,
+    referrer_config: Option<&ReferrerConfig>,
+) -> Result<()> {
+    require!(
+        referrer_config.is_some_and(|config| config.referrer == *referrer),
+        BridgeError::ReferrerNotRegistered
+    );
+
+    Ok(())
+}
+
+/// Whether `partner` signed for `partner_config`; `partner_config` constraints
+/// only run when it is passed.
+fn signed_by_partner(partner_config: &PartnerConfig, partner: &Option<Signer>) -> bool {
+    partner.as_ref().is_some_and(|partner| partner.key() == partner_config.partner)
+}
+
+/// Checks, funds and books a single SOL lock for `lock_sol` and its variants.
+/// Returns the `TokenLocked` event for the caller to `emit_cpi!`.
+fn execute_lock(
+    mut accounts: LockAccounts,
+    amount: u64,
+    unicity_recipient: String,
+    deadline: i64,
+    memo: [u8; 32],
+) -> Result<TokenLocked> {
+    let bridge_state = &mut accounts.bridge_state;
+    bridge_state.validate_recipient(&unicity_recipient)?;
+    bridge_state.check_recipient_checksum(&unicity_recipient)?;
+    bridge_state.check_recipient_binding(accounts.recipient_binding, &unicity_recipient)?;
+    bridge_state.check_locks_open()?;
+    let clock = Clock::get()?;
+    let now = bridge_state.advance_lock_clock(clock.unix_timestamp)?;
+    check_deadline(deadline, now)?;
+    accounts.user_stats.enforce_cooldown(now, bridge_state.min_interval_secs)?;
+    let lock = bridge_state.prepare_lock(
+        amount,
+        accounts.user_stats.total_locked,
+        accounts.partner_config.map(|config| config.fee_bps),
+        now,
+    )?;
+    // Abort on an overflowing total before any lamports move
+    bridge_state.check_lock_headroom(lock.net)?;
+    if let Some(referrer) = accounts.referrer {
+        require_keys_neq!(referrer.key(), accounts.user.key(), BridgeError::InvalidReferrer);
+        check_referrer_registered(referrer.key, accounts.referrer_config)?;
+    }
+    let referral_fee = accounts.referral_fee(&lock)?;
+    let escrowed = lock.net.checked_add(accounts.dest_gas)
+        .ok_or(BridgeError::Overflow)?;
+
+    collect_lock_funds(
+        accounts.user,
+        accounts.escrow,
+        accounts.fee_collector,
+        accounts.referrer.map(|referrer| (referrer, referral_fee)),
+        escrowed,
+        lock.fee,
+    )?;
+    accounts.bridge_state.record_dest_gas(accounts.dest_gas)?;
+
+    let escrow_balance = accounts.escrow.lamports();
+    check_rent_exempt(escrow_balance, Rent::get()?.minimum_balance(accounts.escrow.data_len()))?;
+    let event = record_lock(
+        &mut accounts,
+        &lock,
+        unicity_recipient,
+        memo,
+        &clock,
+        escrow_balance,
+        BatchPosition::SINGLE,
+    )?;
+    accounts.bridge_state.check_solvency(escrow_balance)?;
+    // Plain-text copy of the event's lock_id, for grepping logs
+    msg!("lock_id {}", bs58::encode(event.lock_id).into_string());
+    if let Some(recipient_stats) = accounts.recipient_stats {
+        recipient_stats.record(recipient_key(&event.unicity_recipient), lock.net)?;
+    }
+
+    Ok(event)
+}
+
+/// Moves a lock's funds from `user`: `net` lamports to the escrow and `fee`
+/// lamports to the fee collector, less the `(referrer, referral_fee)` cut.
+fn collect_lock_funds<'info>(
+    user: &Signer<'info>,
+    escrow: &AccountInfo<'info>,
+    fee_collector: &SystemAccount<'info>,
+    referral: Option<(&SystemAccount<'info>, u64)>,
+    net: u64,
+    fee: u64,
+) -> Result<()> {
+    // Transfer SOL from user to escrow
+    let ix = anchor_lang::solana_program::system_instruction::transfer(
+        &user.key(),
+        &escrow.key(),
+        net,
+    );
+    anchor_lang::solana_program::program::invoke(
+        &ix,
+        &[
+            user.to_account_info(),
+            escrow.to_account_info(),
+        ],
+    )?;
+
+    // Pay the referrer its cut of the fee
+    let mut fee = fee;
+    if let Some((referrer, referral_fee)) = referral.filter(|(_, referral_fee)| *referral_fee > 0) {
+        fee = fee.checked_sub(referral_fee)
+            .ok_or(BridgeError::Overflow)?;
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &user.key(),
+            &referrer.key(),
+            referral_fee,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                user.to_account_info(),
+                referrer.to_account_info(),
+            ],
+        )?;
+    }
+
+    // Transfer the bridge fee from user to fee collector
+    if fee > 0 {
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &user.key(),
+            &fee_collector.key(),
+            fee,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                user.to_account_info(),
+                fee_collector.to_account_info(),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Feeds a lock into `BridgeState::record_lock_velocity` and announces the pause
+/// when it trips.
+fn check_lock_velocity(bridge_state: &mut BridgeState, now: i64) -> Result<()> {
+    if bridge_state.record_lock_velocity(now)? {
+        emit!(AutoPaused {
+            window_locks: bridge_state.velocity_window_locks,
+            max_locks_per_window: bridge_state.max_locks_per_window,
+            velocity_window_secs: bridge_state.velocity_window_secs,
+            timestamp: now,
+        });
+    }
+
+    Ok(())
+}
+
+/// Books a funded lock against the bridge and user totals, assigns it the next
+/// nonce and returns its `TokenLocked` event. `clock` must be the one already
+/// checked by `BridgeState::advance_lock_clock`.
+fn record_lock(
+    accounts: &mut LockAccounts,
+    lock: &LockAmounts,
+    unicity_recipient: String,
+    memo: [u8; 32],
+    clock: &Clock,
+    escrow_balance: u64,
+    batch: BatchPosition,
+) -> Result<TokenLocked> {
+    let now = clock.unix_timestamp;
+    let user = accounts.user.key();
+    let referral_fee = accounts.referral_fee(lock)?;
+    let bridge_state = &mut accounts.bridge_state;
+    let user_stats = &mut accounts.user_stats;
+
+    // Update bridge state
+    bridge_state.total_locked = bridge_state.total_locked.checked_add(lock.net)
+        .ok_or(BridgeError::Overflow)?;
+    bridge_state.hot_locked = bridge_state.hot_locked.checked_add(lock.net)
+        .ok_or(BridgeError::Overflow)?;
+    bridge_state.record_volume(lock.net)?;
+    bridge_state.check_tvl_cap()?;
+    bridge_state.nonce = bridge_state.nonce.checked_add(1)
+        .ok_or(BridgeError::Overflow)?;
+    check_lock_velocity(bridge_state, now)?;
+
+    // Update per-user stats
+    user_stats.total_locked = user_stats.total_locked.checked_add(lock.net)
+        .ok_or(BridgeError::Overflow)?;
+    user_stats.lock_count = user_stats.lock_count.checked_add(1)
+        .ok_or(BridgeError::Overflow)?;
+    user_stats.last_nonce = bridge_state.nonce;
+
+    // Create lock event
+    let lock_id = derive_lock_id(
+        &user,
+        bridge_state.nonce,
+        now,
+        lock.net,
+        &unicity_recipient,
+        &memo,
+        batch.index,
+    );
+    let sequence_hash = bridge_state.chain_lock(&lock_id);
+    bridge_state.fold_epoch(&lock_id)?;
+    let unicity_amount = bridge_state.unicity_amount(lock.net)?;
+
+    Ok(TokenLocked {
+        version: EVENT_VERSION,
+        lock_id,
+        user,
+        amount: lock.net,
+        unicity_amount,
+        gross_amount: lock.gross,
+        fee: lock.fee,
+        fee_tier: lock.fee_tier,
+        unicity_recipient,
+        memo,
+        nonce: bridge_state.nonce,
+        prev_nonce: bridge_state.nonce - 1,
+        sequence_hash,
+        timestamp: now,
+        slot: clock.slot,
+        daily_remaining: lock.daily_remaining,
+        total_locked: bridge_state.total_locked,
+        escrow_balance,
+        partner: accounts.partner_config.map(|config| config.partner),
+        fee_payer: accounts.fee_payer,
+        referrer: accounts.referrer.map(|referrer| referrer.key()),
+        referral_fee,
+        dest_gas: accounts.dest_gas,
+        vault_program: accounts.vault_program,
+        batch_index: batch.index,
+        batch_size: batch.size,
+        cluster_tag: bridge_state.cluster_tag,
+    })
+}
+
+/// Identifier a relayer uses to reference a single lock on the Unicity side:
+/// `sha256(user || nonce || timestamp || amount || sha256(unicity_recipient) ||
+/// memo || batch_index)`, with integers little-endian, the recipient prehashed
+/// from its raw UTF-8 bytes, a zero memo for locks made without one and a zero
+/// `batch_index` outside of `lock_sol_batch`. The prehash keeps the preimage at
+/// `LOCK_ID_PREIMAGE_LEN` bytes whatever the recipient length; lock_ids before
+/// `EVENT_VERSION` 11 hashed the raw recipient bytes in its place.
+fn derive_lock_id(
+    user: &Pubkey,
+    nonce: u64,
+    timestamp: i64,
+    amount: u64,
+    unicity_recipient: &str,
+    memo: &[u8; 32],
+    batch_index: u16,
+) -> [u8; 32] {
+    let mut data = Vec::with_capacity(LOCK_ID_PREIMAGE_LEN);
+    data.extend_from_slice(&user.to_bytes());
+    data.extend_from_slice(&nonce.to_le_bytes());
+    data.extend_from_slice(&timestamp.to_le_bytes());
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&hash(unicity_recipient.as_bytes()).to_bytes());
+    data.extend_from_slice(memo);
+    data.extend_from_slice(&batch_index.to_le_bytes());
+    hash(&data).to_bytes()
+}
+
+/// Size of the `derive_lock_id` preimage.
+const LOCK_ID_PREIMAGE_LEN: usize = 32 + 8 + 8 + 8 + 32 + 32 + 2;
+
+/// Checks that `vault` is the PDA of `vault_program` at `vault_seeds`, whose last
+/// seed is the bump.
+fn check_vault(vault_program: &Pubkey, vault_seeds: &[Vec<u8>], vault: &Pubkey) -> Result<()> {
+    require!(*vault_program != Pubkey::default(), BridgeError::VaultProgramNotSet);
+    let seeds: Vec<&[u8]> = vault_seeds.iter().map(Vec::as_slice).collect();
+    let expected = Pubkey::create_program_address(&seeds, vault_program)
+        .map_err(|_| BridgeError::InvalidVault)?;
+    require_keys_eq!(*vault, expected, BridgeError::InvalidVault);
+
+    Ok(())
+}
+
+/// Refuses a lock to `unicity_recipient` unless `blocked_recipient` is its
+/// `[b"blocked_rcpt", recipient_key]` PDA and `block_recipient` has not created it.
+fn check_recipient_not_blocked(
+    program_id: &Pubkey,
+    blocked_recipient: &AccountInfo,
+    unicity_recipient: &str,
+) -> Result<()> {
+    let (expected, _) = Pubkey::find_program_address(
+        &[b"blocked_rcpt", recipient_key(unicity_recipient).as_ref()],
+        program_id,
+    );
+    require_keys_eq!(*blocked_recipient.key, expected, BridgeError::InvalidBlocklistAccount);
+    require!(blocked_recipient.data_is_empty(), BridgeError::RecipientBlocked);
+
+    Ok(())
+}
+
+/// Seed of a recipient's `RecipientStats`: `sha256` of the lowercased address, so
+/// both hex cases of one address share an account.
+pub fn recipient_key(unicity_recipient: &str) -> [u8; 32] {
+    hash(unicity_recipient.to_ascii_lowercase().as_bytes()).to_bytes()
+}
+
+/// Checks that `escrow_key` is the `[b"escrow"]` PDA of `program_id` and returns
+/// its canonical bump, so handler bodies never re-derive the seeds by hand.
+fn assert_escrow(program_id: &Pubkey, escrow_key: &Pubkey) -> Result<u8> {
+    let (expected, bump) = Pubkey::find_program_address(&[b"escrow"], program_id);
+    require_keys_eq!(*escrow_key, expected, BridgeError::InvalidEscrow);
+
+    Ok(bump)
+}
+
+/// Re-checks that `escrow` is the system-owned `[b"escrow"]` PDA of this program,
+/// independently of the seeds constraint on the accounts struct, and returns its
+/// bump. Reached through `EscrowPayer::canonical`, the only way handlers pay out
+/// of the escrow.
+fn assert_canonical_escrow(escrow: &AccountInfo, program_id: &Pubkey) -> Result<u8> {
+    require_keys_eq!(*escrow.owner, System::id(), BridgeError::InvalidEscrow);
+    assert_escrow(program_id, escrow.key)
+}
+
+/// The canonical `[b"escrow"]` PDA with what it takes to sign transfers out of
+/// it. Every payout from the escrow goes through `pay`.
+#[derive(Clone, Copy)]
+struct EscrowPayer<'a, 'info> {
+    escrow: &'a AccountInfo<'info>,
+    /// Bump returned by `assert_canonical_escrow`.
+    escrow_bump: u8,
+    system_program: &'a Program<'info, System>,
+}
+
+impl<'a, 'info> EscrowPayer<'a, 'info> {
+    fn canonical(
+        escrow: &'a AccountInfo<'info>,
+        system_program: &'a Program<'info, System>,
+        program_id: &Pubkey,
+    ) -> Result<Self> {
+        let escrow_bump = assert_canonical_escrow(escrow, program_id)?;
+        Ok(Self { escrow, escrow_bump, system_program })
+    }
+
+    fn pay(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        let seeds: &[&[u8]] = &[b"escrow", &[self.escrow_bump]];
+        transfer_from_pda(self.system_program, self.escrow, to, seeds, amount)
+    }
+}
+
+/// Moves `amount` lamports out of the system-owned PDA `from` with a system
+/// transfer signed by its `seeds`, bump included. The runtime only lets an
+/// account's owner debit it, so a PDA of this program that holds SOL can only
+/// pay out through the system program.
+fn transfer_from_pda<'info>(
+    system_program: &Program<'info, System>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    anchor_lang::system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )
+}
+
+/// Rejects an emergency withdraw whose `admin` is also passed as the escrow or the
+/// bridge state, which would pay the escrow back to itself or into program state.
+fn check_not_aliased(admin: &Pubkey, escrow: &Pubkey, bridge_state: &Pubkey) -> Result<()> {
+    require_keys_neq!(*escrow, *admin, BridgeError::AliasedAccounts);
+    require_keys_neq!(*bridge_state, *admin, BridgeError::AliasedAccounts);
+
+    Ok(())
+}
+
+/// Rejects a lock landing after `deadline`; a deadline of 0 never expires.
+fn check_deadline(deadline: i64, now: i64) -> Result<()> {
+    require!(deadline == 0 || now <= deadline, BridgeError::DeadlineExceeded);
+
+    Ok(())
+}
+
+/// Checks that `splits` fit `MAX_WITHDRAW_SPLITS`, name distinct recipients with
+/// non-zero shares and add up to `BPS_DENOMINATOR`; an empty list is valid.
+fn validate_withdraw_splits(splits: &[WithdrawSplit]) -> Result<()> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+    let total: u64 = splits.iter().map(|split| split.bps as u64).sum();
+    require!(
+        splits.len() <= MAX_WITHDRAW_SPLITS
+            && total == BPS_DENOMINATOR
+            && splits.iter().all(|split| split.bps > 0)
+            && splits.iter().enumerate().all(|(i, split)| {
+                splits[..i].iter().all(|earlier| earlier.recipient != split.recipient)
+            }),
+        BridgeError::InvalidWithdrawSplits
+    );
+
+    Ok(())
+}
+
+/// Pays an emergency withdraw of `amount` out of `escrow`: all to `admin` when
+/// no `withdraw_splits` are configured, otherwise to each split recipient, which
+/// `recipients` must list in configured order. Emits `EmergencyWithdrawalSplit`
+/// per recipient.
+fn pay_emergency_withdraw<'info>(
+    bridge_state: &BridgeState,
+    payer: EscrowPayer<'_, 'info>,
+    admin: &AccountInfo<'info>,
+    recipients: &[AccountInfo<'info>],
+    amount: u64,
+    timestamp: i64,
+) -> Result<()> {
+    let splits = bridge_state.configured_withdraw_splits();
+    if splits.is_empty() {
+        return payer.pay(admin, amount);
+    }
+    require!(recipients.len() == splits.len(), BridgeError::InvalidWithdrawSplitAccount);
+
+    let shares = bridge_state.withdraw_split_amounts(amount)?;
+    for ((split, recipient), share) in splits.iter().zip(recipients).zip(shares) {
+        require_keys_eq!(
+            recipient.key(),
+            split.recipient,
+            BridgeError::InvalidWithdrawSplitAccount
+        );
+        payer.pay(recipient, share)?;
+        if bridge_state.emits(EMIT_WITHDRAWAL) {
+            emit!(EmergencyWithdrawalSplit {
+                recipient: split.recipient,
+                bps: split.bps,
+                amount: share,
+                timestamp,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Admin recorded in raw `bridge_state` data of any size, so even an account too
+/// small to deserialize as the current `BridgeState` can be authorized against.
+/// `admin` is the first field, right after the discriminator.
+fn state_admin(data: &[u8]) -> Result<Pubkey> {
+    require!(data.starts_with(BridgeState::DISCRIMINATOR), BridgeError::InvalidStateAccount);
+    let admin = data.get(8..40).ok_or(BridgeError::InvalidStateAccount)?;
+
+    Ok(Pubkey::try_from(admin).map_err(|_| BridgeError::InvalidStateAccount)?)
+}
+
+/// Lamports in the hot escrow beyond its rent reserve and what it is `committed`
+/// to, see `BridgeState::hot_escrow_committed`.
+fn escrow_surplus(escrow_lamports: u64, rent_minimum: u64, committed: u64) -> u64 {
+    escrow_lamports
+        .saturating_sub(rent_minimum)
+        .saturating_sub(committed)
+}
+
+/// Lamports `collect_fees` moves out of a fee vault holding `balance`: `amount`,
+/// or all of it above `rent_minimum` when `amount` is 0.
+fn collectable_fees(balance: u64, rent_minimum: u64, amount: u64) -> Result<u64> {
+    let available = balance.saturating_sub(rent_minimum);
+    require!(available > 0, BridgeError::NothingToWithdraw);
+    if amount == 0 {
+        return Ok(available);
+    }
+    require!(amount <= available, BridgeError::InsufficientFees);
+
+    Ok(amount)
+}
+
+/// An escrow `surplus` `sweep_dust` may move: non-zero and below `dust_threshold`.
+fn sweepable_dust(surplus: u64, dust_threshold: u64) -> Result<u64> {
+    require!(surplus > 0, BridgeError::NothingToWithdraw);
+    require!(surplus < dust_threshold, BridgeError::SurplusAboveDustThreshold);
+
+    Ok(surplus)
+}
+
+/// Fails with `EscrowNotRentExempt` unless an escrow holding `balance` covers
+/// its `rent_minimum`. The first lock on a fresh deployment creates the escrow,
+/// and a dust amount would otherwise leave it below rent exemption.
+fn check_rent_exempt(balance: u64, rent_minimum: u64) -> Result<()> {
+    require!(balance >= rent_minimum, BridgeError::EscrowNotRentExempt);
+
+    Ok(())
+}
+
+/// Lamports held by `escrow` above its rent-exempt minimum.
+fn withdrawable_balance(escrow: &AccountInfo) -> Result<u64> {
+    let rent_minimum = Rent::get()?.minimum_balance(escrow.data_len());
+    Ok(escrow.lamports().saturating_sub(rent_minimum))
+}
+
+/// Checks that `s` is a Unicity address: between `min_len` and `max_len` hex
+/// characters (either case), without the `[SHA256]` prefix used by wallets.
+/// `max_len` is capped at `MAX_RECIPIENT_LEN`. Lengths are in bytes, which is
+/// also what the account and event encodings bound; since only ASCII hex passes,
+/// a multi-byte character is rejected rather than counted differently.
+pub fn validate_unicity_address(s: &str, min_len: usize, max_len: usize) -> Result<()> {
+    let max_len = max_len.min(MAX_RECIPIENT_LEN);
+    if !(min_len..=max_len).contains(&s.len()) {
+        msg!(
+            "unicity_recipient is {} bytes, expected {} to {}",
+            s.len(),
+            min_len,
+            max_len
+        );
+        return err!(BridgeError::InvalidRecipient);
+    }
+    if let Some(position) = s.bytes().position(|b| !b.is_ascii_hexdigit()) {
+        msg!("unicity_recipient has a non-hex character at byte {}", position);
+        return err!(BridgeError::InvalidRecipient);
+    }
+
+    Ok(())
+}
+
+/// Checks the mixed-case checksum of a hex Unicity address, in the style of
+/// EIP-55 but over sha256: with `h = sha256(lowercase(s))`, the letter at byte
+/// `i` must be uppercase exactly when nibble `i % 64` of `h` (high nibble of
+/// each byte first) is 8 or more. Digits carry no case and are not checked.
+pub fn verify_recipient_checksum(s: &str) -> Result<()> {
+    let digest = hash(s.to_ascii_lowercase().as_bytes()).to_bytes();
+    for (i, c) in s.bytes().enumerate() {
+        if !c.is_ascii_alphabetic() {
+            continue;
+        }
+        let byte = digest[(i % 64) / 2];
+        let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0x0f };
+        if c.is_ascii_uppercase() != (nibble >= 8) {
+            msg!("unicity_recipient has a wrong checksum case at byte {}", i);
+            return err!(BridgeError::InvalidRecipientChecksum);
+        }
+    }
+
+    Ok(())
+}
+
+/// Bridge fee charged on a lock of `amount` lamports at `fee_bps`, rounded down.
+pub fn compute_fee(amount: u64, fee_bps: u16) -> Result<u64> {
+    let fee = (amount as u128)
+        .checked_mul(fee_bps as u128)
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR as u128))
+        .ok_or(BridgeError::Overflow)?;
+
+    u64::try_from(fee).map_err(|_| BridgeError::Overflow.into())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BridgeState::INIT_SPACE,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetState<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+}
+
+#[derive(Accounts)]
+pub struct CheckInvariants<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(
+        seeds = [b"escrow_cold"],
+        bump
+    )]
+    /// CHECK: Cold counterpart of `escrow`, also a plain SOL-holding PDA
+    pub cold_escrow: AccountInfo<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, unicity_recipient: String)]
+pub struct LockSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        address = bridge_state.fee_collector @ BridgeError::InvalidFeeCollector
+    )]
+    pub fee_collector: SystemAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+    
+    #[account(
+        seeds = [b"denied", user.key().as_ref()],
+        bump,
+        constraint = denied_user.data_is_empty() @ BridgeError::UserDenied
+    )]
+    /// CHECK: Only ever created by `deny_user`; the lock is refused while it exists
+    pub denied_user: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"blocked_rcpt", recipient_key(&unicity_recipient).as_ref()],
+        bump,
+        constraint = blocked_recipient.data_is_empty() @ BridgeError::RecipientBlocked
+    )]
+    /// CHECK: Only ever created by `block_recipient`; the lock is refused while it exists
+    pub blocked_recipient: UncheckedAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RecipientStats::INIT_SPACE,
+        seeds = [b"recipient", recipient_key(&unicity_recipient).as_ref()],
+        bump
+    )]
+    pub recipient_stats: Account<'info, RecipientStats>,
+    
+    /// Only `register_partner` creates accounts of this type, so any one passed
+    /// here is a registered partner. Its rate needs that partner's signature.
+    #[account(
+        constraint = signed_by_partner(partner_config, &partner) @ BridgeError::PartnerNotSigner
+    )]
+    pub partner_config: Option<Account<'info, PartnerConfig>>,
+    
+    /// The `PartnerConfig::partner` of `partner_config`, co-signing the lock.
+    pub partner: Option<Signer<'info>>,
+    
+    /// Receives the `referral_bps` cut of the fee; must not be `user`.
+    #[account(mut)]
+    pub referrer: Option<SystemAccount<'info>>,
+    
+    /// Required with `referrer`: its registration by `register_referrer`.
+    pub referrer_config: Option<Account<'info, ReferrerConfig>>,
+    
+    /// Required while `bindings_required` is set, see `bind_recipient`.
+    #[account(
+        seeds = [b"binding", user.key().as_ref()],
+        bump
+    )]
+    pub recipient_binding: Option<Account<'info, RecipientBinding>>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, unicity_recipient: String)]
+pub struct LockSolSponsored<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        address = bridge_state.fee_collector @ BridgeError::InvalidFeeCollector
+    )]
+    pub fee_collector: SystemAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = fee_payer,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+    
+    #[account(
+        seeds = [b"denied", user.key().as_ref()],
+        bump,
+        constraint = denied_user.data_is_empty() @ BridgeError::UserDenied
+    )]
+    /// CHECK: Only ever created by `deny_user`; the lock is refused while it exists
+    pub denied_user: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"blocked_rcpt", recipient_key(&unicity_recipient).as_ref()],
+        bump,
+        constraint = blocked_recipient.data_is_empty() @ BridgeError::RecipientBlocked
+    )]
+    /// CHECK: Only ever created by `block_recipient`; the lock is refused while it exists
+    pub blocked_recipient: UncheckedAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = fee_payer,
+        space = 8 + RecipientStats::INIT_SPACE,
+        seeds = [b"recipient", recipient_key(&unicity_recipient).as_ref()],
+        bump
+    )]
+    pub recipient_stats: Account<'info, RecipientStats>,
+    
+    /// Only `register_partner` creates accounts of this type, so any one passed
+    /// here is a registered partner. Its rate needs that partner's signature.
+    #[account(
+        constraint = signed_by_partner(partner_config, &partner) @ BridgeError::PartnerNotSigner
+    )]
+    pub partner_config: Option<Account<'info, PartnerConfig>>,
+    
+    /// The `PartnerConfig::partner` of `partner_config`, co-signing the lock.
+    pub partner: Option<Signer<'info>>,
+    
+    /// Funds the lock and its fee.
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// Pays for the transaction and the `UserStats` rent.
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct LockSolBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        address = bridge_state.fee_collector @ BridgeError::InvalidFeeCollector
+    )]
+    pub fee_collector: SystemAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+    
+    #[account(
+        seeds = [b"denied", user.key().as_ref()],
+        bump,
+        constraint = denied_user.data_is_empty() @ BridgeError::UserDenied
+    )]
+    /// CHECK: Only ever created by `deny_user`; the lock is refused while it exists
+    pub denied_user: UncheckedAccount<'info>,
+    
+    /// Only `register_partner` creates accounts of this type, so any one passed
+    /// here is a registered partner. Its rate needs that partner's signature.
+    #[account(
+        constraint = signed_by_partner(partner_config, &partner) @ BridgeError::PartnerNotSigner
+    )]
+    pub partner_config: Option<Account<'info, PartnerConfig>>,
+    
+    /// The `PartnerConfig::partner` of `partner_config`, co-signing the lock.
+    pub partner: Option<Signer<'info>>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, unicity_recipient: String, deadline: i64, client_key: [u8; 32])]
+pub struct LockSolIdempotent<'info> {
+    pub lock: LockSol<'info>,
+    
+    #[account(
+        init,
+        payer = lock.user,
+        space = 8 + LockReceipt::INIT_SPACE,
+        seeds = [b"lock", lock.user.key().as_ref(), client_key.as_ref()],
+        bump
+    )]
+    pub lock_receipt: Account<'info, LockReceipt>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, unicity_recipient: String, deadline: i64, receipt_key: [u8; 32])]
+pub struct LockSolWithReceipt<'info> {
+    pub lock: LockSol<'info>,
+    
+    #[account(
+        init,
+        payer = lock.user,
+        space = 8 + PendingLock::INIT_SPACE,
+        seeds = [b"pending", lock.user.key().as_ref(), receipt_key.as_ref()],
+        bump
+    )]
+    pub pending_lock: Account<'info, PendingLock>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePendingLock<'info> {
+    #[account(
+        mut,
+        close = user,
+        has_one = user @ BridgeError::InvalidRecipient
+    )]
+    pub pending_lock: Account<'info, PendingLock>,
+    
+    /// Only exists once the lock was minted, refunded or cancelled.
+    #[account(
+        seeds = [b"lock_processed", pending_lock.lock_id.as_ref()],
+        bump
+    )]
+    pub processed_lock: Account<'info, ProcessedLock>,
+    
+    #[account(mut)]
+    pub user: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, unicity_recipient: String)]
+pub struct LockToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    /// Escrow for this mint. Native SOL keeps the single-seed `[b"escrow"]` PDA,
+    /// which cannot collide with these two-seed addresses. Tokens locked before
+    /// the switch from `[b"token_escrow", mint]` stay in those older accounts,
+    /// which no instruction releases from.
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"escrow", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = bridge_state
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + AssetStats::INIT_SPACE,
+        seeds = [b"asset", mint.key().as_ref()],
+        bump
+    )]
+    pub asset_stats: Account<'info, AssetStats>,
+    
+    #[account(
+        seeds = [b"denied", user.key().as_ref()],
+        bump,
+        constraint = denied_user.data_is_empty() @ BridgeError::UserDenied
+    )]
+    /// CHECK: Only ever created by `deny_user`; the lock is refused while it exists
+    pub denied_user: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"blocked_rcpt", recipient_key(&unicity_recipient).as_ref()],
+        bump,
+        constraint = blocked_recipient.data_is_empty() @ BridgeError::RecipientBlocked
+    )]
+    /// CHECK: Only ever created by `block_recipient`; the lock is refused while it exists
+    pub blocked_recipient: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, recipient: Pubkey, unicity_tx_id: [u8; 32])]
+pub struct ReleaseSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProcessedRelease::INIT_SPACE,
+        seeds = [b"processed", unicity_tx_id.as_ref()],
+        bump
+    )]
+    pub processed_release: Account<'info, ProcessedRelease>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, recipient: Pubkey, unicity_tx_id: [u8; 32])]
+pub struct EnqueueRelease<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProcessedRelease::INIT_SPACE,
+        seeds = [b"processed", unicity_tx_id.as_ref()],
+        bump
+    )]
+    pub processed_release: Account<'info, ProcessedRelease>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + QueuedRelease::INIT_SPACE,
+        seeds = [b"queued_release", bridge_state.release_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub queued_release: Account<'info, QueuedRelease>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessRelease<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = payer,
+        has_one = recipient @ BridgeError::InvalidRecipient,
+        has_one = payer,
+        seeds = [b"queued_release", bridge_state.next_release_to_process.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub queued_release: Account<'info, QueuedRelease>,
+    
+    #[account(
+        mut,
+        seeds = [b"processed", queued_release.unicity_tx_id.as_ref()],
+        bump
+    )]
+    pub processed_release: Account<'info, ProcessedRelease>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    
+    /// Receives the queue entry's rent back.
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelQueuedRelease<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        seeds = [b"queued_release", bridge_state.next_release_to_process.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub queued_release: Account<'info, QueuedRelease>,
+    
+    /// Reserved by `enqueue_release` and still unpaid.
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"processed", queued_release.unicity_tx_id.as_ref()],
+        bump,
+        constraint = processed_release.processed_at == 0 @ BridgeError::ReleaseAlreadyProcessed
+    )]
+    pub processed_release: Account<'info, ProcessedRelease>,
+    
+    /// Receives the rent of both accounts back.
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, recipient: Pubkey, unicity_tx_id: [u8; 32])]
+pub struct ReleaseToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProcessedRelease::INIT_SPACE,
+        seeds = [b"processed", unicity_tx_id.as_ref()],
+        bump
+    )]
+    pub processed_release: Account<'info, ProcessedRelease>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", mint.key().as_ref()],
+        bump
+    )]
+    pub asset_stats: Account<'info, AssetStats>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = bridge_state
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = recipient
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, recipient: Pubkey, unicity_tx_id: [u8; 32])]
+pub struct ClaimWithSignature<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProcessedRelease::INIT_SPACE,
+        seeds = [b"processed", unicity_tx_id.as_ref()],
+        bump
+    )]
+    pub processed_release: Account<'info, ProcessedRelease>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Address is constrained to the instructions sysvar
+    pub instructions: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: [u8; 32])]
+pub struct RequestRefund<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + RefundRequest::INIT_SPACE,
+        seeds = [b"refund", user.key().as_ref(), lock_id.as_ref()],
+        bump
+    )]
+    pub refund_request: Account<'info, RefundRequest>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: [u8; 32])]
+pub struct RefundLock<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = user,
+        has_one = user @ BridgeError::InvalidRecipient,
+        seeds = [b"refund", user.key().as_ref(), lock_id.as_ref()],
+        bump
+    )]
+    pub refund_request: Account<'info, RefundRequest>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProcessedLock::INIT_SPACE,
+        seeds = [b"lock_processed", lock_id.as_ref()],
+        bump
+    )]
+    pub processed_lock: Account<'info, ProcessedLock>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub user: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: [u8; 32])]
+pub struct CancelLock<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = user,
+        has_one = user @ BridgeError::Unauthorized,
+        constraint = lock_receipt.lock_id == lock_id @ BridgeError::InvalidLockReceipt
+    )]
+    pub lock_receipt: Account<'info, LockReceipt>,
+    
+    /// Fails to init once the lock was refunded, cancelled or marked processed.
+    #[account(
+        init,
+        payer = user,
+        space = 8 + ProcessedLock::INIT_SPACE,
+        seeds = [b"lock_processed", lock_id.as_ref()],
+        bump
+    )]
+    pub processed_lock: Account<'info, ProcessedLock>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(lock_id: [u8; 32])]
+pub struct MarkLockProcessed<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProcessedLock::INIT_SPACE,
+        seeds = [b"lock_processed", lock_id.as_ref()],
+        bump
+    )]
+    pub processed_lock: Account<'info, ProcessedLock>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RollEpoch<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + EpochCommitment::INIT_SPACE,
+        seeds = [b"epoch", bridge_state.current_epoch.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub epoch_commitment: Account<'info, EpochCommitment>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(partner: Pubkey)]
+pub struct RegisterPartner<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + PartnerConfig::INIT_SPACE,
+        seeds = [b"partner", partner.as_ref()],
+        bump
+    )]
+    pub partner_config: Account<'info, PartnerConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct BindRecipient<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + RecipientBinding::INIT_SPACE,
+        seeds = [b"binding", user.as_ref()],
+        bump
+    )]
+    pub recipient_binding: Account<'info, RecipientBinding>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct DenyUser<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + DeniedUser::INIT_SPACE,
+        seeds = [b"denied", user.as_ref()],
+        bump
+    )]
+    pub denied_user: Account<'info, DeniedUser>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AllowUser<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"denied", user.as_ref()],
+        bump
+    )]
+    pub denied_user: Account<'info, DeniedUser>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(unicity_recipient: String)]
+pub struct BlockRecipient<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + BlockedRecipient::INIT_SPACE,
+        seeds = [b"blocked_rcpt", recipient_key(&unicity_recipient).as_ref()],
+        bump
+    )]
+    pub blocked_recipient: Account<'info, BlockedRecipient>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(unicity_recipient: String)]
+pub struct UnblockRecipient<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"blocked_rcpt", recipient_key(&unicity_recipient).as_ref()],
+        bump
+    )]
+    pub blocked_recipient: Account<'info, BlockedRecipient>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// Must be `bridge_state.guardian` whenever one is configured.
+    pub guardian: Option<Signer<'info>>,
+    
+    /// Signs the escrow's payouts, see `transfer_from_pda`.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmergencyNonceBinding<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ScheduleEmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelEmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RebalanceEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_cold"],
+        bump
+    )]
+    /// CHECK: Cold counterpart of `escrow`, also a plain SOL-holding PDA
+    pub cold_escrow: AccountInfo<'info>,
+    
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Reconcile<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(
+        mut,
+        address = bridge_state.fee_collector @ BridgeError::InvalidFeeCollector
+    )]
+    pub fee_collector: SystemAccount<'info>,
+    
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"fee_vault"],
+        bump
+    )]
+    pub fee_vault: SystemAccount<'info>,
+    
+    #[account(
+        mut,
+        address = bridge_state.treasury @ BridgeError::InvalidTreasury
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdjustAccounting<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResetNonce<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBridge<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        close = admin
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow"],
+        bump
+    )]
+    /// CHECK: This is safe as it's just an escrow account holding SOL
+    pub escrow: AccountInfo<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDustThreshold<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferralBps<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ReferrerConfig::INIT_SPACE,
+        seeds = [b"referrer", referrer.as_ref()],
+        bump
+    )]
+    pub referrer_config: Account<'info, ReferrerConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct RemoveReferrer<'info> {
+    #[account(
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"referrer", referrer.as_ref()],
+        bump
+    )]
+    pub referrer_config: Account<'info, ReferrerConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultProgram<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRelayerReward<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    /// Taken unchecked because an outdated account does not deserialize; the
+    /// handler checks its discriminator through `state_admin`.
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump,
+        owner = crate::ID
+    )]
+    /// CHECK: Raw `BridgeState` data, see above
+    pub bridge_state: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLimits<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDailyLimit<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRelayer<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetValidators<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRefundDelay<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeTiers<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCancelWindow<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUnicityDecimals<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTvlCap<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmitFlags<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetChecksumRequired<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRecipientRules<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinInterval<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFee<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub admin: Signer<'info>,
+}
+
+/// Global bridge configuration and accounting.
+///
+/// Fields are only ever appended, and `reserved` always stays last. A new field
+/// goes right before it and `reserved` shrinks by the field's `INIT_SPACE`, so
+/// the account size stays put and an existing `bridge_state` decodes the new
+/// field from what used to be zeroed reserved bytes. Only fixed-size fields whose
+/// all-zero encoding is a sensible default (0, `false`, `None`, an unused slot)
+/// can be added this way.
+///
+/// A field larger than what is left of `reserved` grows the account instead, and
+/// an existing `bridge_state` is then too small for the new layout until
+/// `migrate_state` has run. That last happened with `rotate_relayer`: the 26
+/// bytes left could not hold `next_relayer`, so every `bridge_state` created
+/// before it, or before `reserved` existed, has to be migrated after upgrading
+/// the program. `reserved` was refilled then, so later fields fit again.
+#[account]
+#[derive(InitSpace)]
+pub struct BridgeState {
+    pub admin: Pubkey,
+    /// Cumulative amount ever locked, net of fees; releases are tracked in `total_released`.
+    pub total_locked: u64,
+    pub nonce: u64,
+    pub paused: bool,
+    pub pending_admin: Option<Pubkey>,
+    pub min_lock_amount: u64,
+    /// 0 means no maximum.
+    pub max_lock_amount: u64,
+    /// 0 means no daily cap.
+    pub daily_limit: u64,
+    pub daily_locked: u64,
+    pub daily_window_start: i64,
+    /// Fee charged on each lock, in basis points of the locked amount.
+    pub fee_bps: u16,
+    pub fee_collector: Pubkey,
+    /// Key whose signature authorizes `claim_with_signature`; unset by default.
+    pub relayer_pubkey: Pubkey,
+    /// Validator set for `release_sol_multisig`.
+    #[max_len(MAX_VALIDATORS)]
+    pub validators: Vec<Pubkey>,
+    /// Distinct validator signatures required per release; 0 disables the multisig path.
+    pub threshold: u8,
+    /// Canonical bump of the `[b"bridge_state"]` PDA.
+    pub bridge_bump: u8,
+    /// Canonical bump of the `[b"escrow"]` PDA.
+    pub escrow_bump: u8,
+    /// Timestamp of the most recent lock; lock timestamps never go backwards.
+    pub last_lock_ts: i64,
+    /// Seconds a refund request must wait before the admin can approve it.
+    pub refund_delay: i64,
+    /// Cumulative amount paid out of the escrow by releases and refunds.
+    pub total_released: u64,
+    /// Head of the lock hash chain, see `chain_lock`.
+    pub last_sequence_hash: [u8; 32],
+    /// Per-wallet cooldown between locks, in seconds; 0 disables it.
+    pub min_interval_secs: i64,
+    /// Accepted Unicity recipient length range in bytes, see `set_recipient_rules`.
+    pub recipient_min_len: u16,
+    pub recipient_max_len: u16,
+    /// Delay between scheduling and executing an emergency withdraw, fixed at initialize.
+    pub timelock_secs: i64,
+    /// When the scheduled emergency withdraw becomes executable; 0 when none is scheduled.
+    pub emergency_eta: i64,
+    /// Part of `net_locked` held by the hot `[b"escrow"]`, which funds releases.
+    pub hot_locked: u64,
+    /// Part of `net_locked` held by the cold `[b"escrow_cold"]`.
+    pub cold_locked: u64,
+    /// Volume-based fee discounts, see `set_fee_tiers`.
+    #[max_len(MAX_FEE_TIERS)]
+    pub fee_tiers: Vec<FeeTier>,
+    /// Id the next `enqueue_release` assigns.
+    pub release_nonce: u64,
+    /// Id of the oldest queued release still waiting for `process_release`.
+    pub next_release_to_process: u64,
+    /// Which optional events are emitted, as `EMIT_*` bits.
+    pub emit_flags: u8,
+    /// Ceiling on `net_locked` above which locks are refused; 0 means uncapped.
+    pub tvl_cap: u64,
+    /// Second signer required for emergency withdraws; the default key means none.
+    pub guardian: Pubkey,
+    /// How long after a lock `cancel_lock` still accepts it; 0 disables cancelling.
+    pub cancel_window_secs: i64,
+    /// Decimals Unicity amounts are expressed in, see `unicity_amount`.
+    pub unicity_decimals: u8,
+    /// Share of each `claim_with_signature` and `release_sol_multisig` release paid
+    /// to the submitting relayer, in basis points.
+    pub relayer_reward_bps: u16,
+    /// Stops new locks while releases continue, see `set_pause_flags`.
+    pub locks_paused: bool,
+    /// Stops releases while locks continue, see `set_pause_flags`.
+    pub releases_paused: bool,
+    /// Epoch whose locks `epoch_root` is folding, finalized by `roll_epoch`.
+    pub current_epoch: u64,
+    /// Commitment to the current epoch's lock_ids, see `fold_epoch`.
+    pub epoch_root: [u8; 32],
+    /// Locks folded into `epoch_root` so far.
+    pub epoch_lock_count: u64,
+    /// Program whose PDAs may lock through `lock_sol_from_pda`; the default key means none.
+    pub vault_program: Pubkey,
+    /// Operator-chosen tag of the cluster this deployment runs on, set at `initialize`
+    /// and stamped into `TokenLocked` and `TokenReleased` so a relayer watching
+    /// several deployments can tell their events apart.
+    pub cluster_tag: u8,
+    /// Set for good by `kill_bridge`; no further locks are accepted.
+    pub killed: bool,
+    /// Locks must spell their recipient with its `verify_recipient_checksum` casing.
+    pub checksum_required: bool,
+    /// Destination of `collect_fees`; the default key means none is set.
+    pub treasury: Pubkey,
+    /// Lamports swept from the fee vault to `treasury` so far.
+    pub total_fees_collected: u64,
+    /// Exclusive upper bound on the escrow surplus `sweep_dust` moves; 0 disables it.
+    pub dust_threshold: u64,
+    /// Part of a lock's fee paid to the `referrer` passed with it, in basis points
+    /// of the locked amount; at most `fee_bps`.
+    pub referral_bps: u16,
+    /// `nonce` when the pending emergency withdraw was scheduled.
+    pub emergency_nonce: u64,
+    /// Refuse a scheduled emergency withdraw once a lock landed after it was
+    /// scheduled, see `set_emergency_nonce_binding`.
+    pub emergency_binds_nonce: bool,
+    /// Lamports prepaid through `lock_sol` for minting gas on Unicity. They sit in
+    /// the hot escrow next to `hot_locked` but are neither bridged nor surplus.
+    pub total_dest_gas: u64,
+    /// Relayer taking over from `relayer_pubkey` through `rotate_relayer`; the
+    /// default key means no rotation is pending.
+    pub next_relayer: Pubkey,
+    /// End of the rotation grace window, after which only `next_relayer` signs.
+    pub relayer_rotation_eta: i64,
+    /// Locks allowed per `velocity_window_secs` before the bridge pauses itself,
+    /// see `set_lock_velocity_limit`; 0 disables the trip.
+    pub max_locks_per_window: u32,
+    pub velocity_window_secs: i64,
+    /// Start of the current velocity window and the locks counted in it.
+    pub velocity_window_start: i64,
+    pub velocity_window_locks: u32,
+    /// All-time SOL volume locked, net of fees and including wrapped SOL. Unlike
+    /// `total_locked` nothing ever lowers it, not even `adjust_total_locked`.
+    pub lifetime_volume: u64,
+    /// Locks must go to the user's `RecipientBinding`, see `set_bindings_required`.
+    pub bindings_required: bool,
+    /// Recipients emergency withdraws are split across, see `set_withdraw_splits`.
+    /// Unused slots stay zeroed; with none configured the admin is paid.
+    pub withdraw_splits: [WithdrawSplit; MAX_WITHDRAW_SPLITS],
+    /// Zeroed padding new fields are carved out of; see the struct docs.
+    pub reserved: [u8; STATE_RESERVED_LEN],
+}
+
+impl BridgeState {
+    /// Validates a Unicity recipient against the configured length range.
+    pub fn validate_recipient(&self, unicity_recipient: &str) -> Result<()> {
+        validate_unicity_address(
+            unicity_recipient,
+            self.recipient_min_len as usize,
+            self.recipient_max_len as usize,
+        )
+    }
+
+    /// Checks a lock recipient's checksum casing when `checksum_required` is set.
+    pub fn check_recipient_checksum(&self, unicity_recipient: &str) -> Result<()> {
+        if self.checksum_required {
+            verify_recipient_checksum(unicity_recipient)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks a lock recipient against the user's `binding` when
+    /// `bindings_required` is set; a missing binding fails like a mismatched one.
+    pub fn check_recipient_binding(
+        &self,
+        binding: Option<&RecipientBinding>,
+        unicity_recipient: &str,
+    ) -> Result<()> {
+        if self.bindings_required {
+            require!(
+                binding.is_some_and(|b| b.recipient_hash == recipient_key(unicity_recipient)),
+                BridgeError::RecipientNotBound
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Whether the events behind the `EMIT_*` bit `flag` are enabled.
+    pub fn emits(&self, flag: u8) -> bool {
+        self.emit_flags & flag != 0
+    }
+
+    /// Live escrow liability: `total_locked - total_released`.
+    pub fn net_locked(&self) -> Result<u64> {
+        self.total_locked
+            .checked_sub(self.total_released)
+            .ok_or(BridgeError::AccountingUnderflow.into())
+    }
+
+    /// Requires `signer` to be the configured guardian, if any, and returns it.
+    pub fn check_guardian(&self, signer: Option<Pubkey>) -> Result<Option<Pubkey>> {
+        if self.guardian == Pubkey::default() {
+            return Ok(None);
+        }
+        require!(signer == Some(self.guardian), BridgeError::GuardianRequired);
+
+        Ok(Some(self.guardian))
+    }
+
+    /// Converts a lamport amount into Unicity units by the difference between
+    /// `unicity_decimals` and `SOL_DECIMALS`. Scaling down rounds toward zero;
+    /// scaling up fails with `Overflow` rather than wrapping.
+    pub fn unicity_amount(&self, lamports: u64) -> Result<u64> {
+        let shift = u32::from(self.unicity_decimals.abs_diff(SOL_DECIMALS));
+        let factor = 10u64.checked_pow(shift).ok_or(BridgeError::Overflow)?;
+        if self.unicity_decimals >= SOL_DECIMALS {
+            Ok(lamports.checked_mul(factor).ok_or(BridgeError::Overflow)?)
+        } else {
+            Ok(lamports / factor)
+        }
+    }
+
+    /// Fails with `Overflow` if booking `net` more lamports would overflow
+    /// `total_locked` or `hot_locked`. Locks call it before moving any funds, so
+    /// such a lock aborts without a transfer CPI.
+    pub fn check_lock_headroom(&self, net: u64) -> Result<()> {
+        self.total_locked.checked_add(net)
+            .ok_or(BridgeError::Overflow)?;
+        self.hot_locked.checked_add(net)
+            .ok_or(BridgeError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Refuses a lock once the bridge is killed, or while it or the lock direction
+    /// is paused.
+    pub fn check_locks_open(&self) -> Result<()> {
+        require!(!self.killed, BridgeError::BridgeKilled);
+        require!(!self.paused, BridgeError::BridgePaused);
+        require!(!self.locks_paused, BridgeError::LocksPaused);
+
+        Ok(())
+    }
+
+    /// Refuses a release while the bridge or the release direction is paused.
+    pub fn check_releases_open(&self) -> Result<()> {
+        require!(!self.paused, BridgeError::BridgePaused);
+        require!(!self.releases_paused, BridgeError::ReleasesPaused);
+
+        Ok(())
+    }
+
+    /// Refuses a lock that took `net_locked` past `tvl_cap`.
+    pub fn check_tvl_cap(&self) -> Result<()> {
+        require!(
+            self.tvl_cap == 0 || self.net_locked()? <= self.tvl_cap,
+            BridgeError::TvlCapReached
+        );
+
+        Ok(())
+    }
+
+    /// Requires the hot escrow to hold at least `hot_locked`, so an accounting bug
+    /// fails the lock instead of leaving a silent shortfall.
+    pub fn check_solvency(&self, escrow_lamports: u64) -> Result<()> {
+        require!(escrow_lamports >= self.hot_locked, BridgeError::SolvencyViolation);
+
+        Ok(())
+    }
+
+    /// Checks that releases never exceed locks, that each escrow holds its share
+    /// of `net_locked` on top of its rent reserve, and that the validator
+    /// threshold and fees are within bounds. Returns `net_locked`.
+    pub fn check_invariants(
+        &self,
+        hot_lamports: u64,
+        cold_lamports: u64,
+        rent_minimum: u64,
+    ) -> Result<u64> {
+        let net_locked = self.net_locked()?;
+        let escrows = [(hot_lamports, self.hot_locked), (cold_lamports, self.cold_locked)];
+        for (lamports, liability) in escrows {
+            // An escrow that owes nothing may never have been funded at all.
+            if liability > 0 {
+                let required = liability.checked_add(rent_minimum)
+                    .ok_or(BridgeError::Overflow)?;
+                require!(lamports >= required, BridgeError::SolvencyViolation);
+            }
+        }
+        require!(
+            self.threshold as usize <= self.validators.len(),
+            BridgeError::InvalidThreshold
+        );
+        require!(
+            self.fee_bps <= MAX_FEE_BPS
+                && self.fee_tiers.iter().all(|tier| tier.fee_bps <= MAX_FEE_BPS),
+            BridgeError::FeeTooHigh
+        );
+
+        Ok(net_locked)
+    }
+
+    /// Takes a cancelled lock of `amount` back out of `total_locked` and
+    /// `hot_locked`, as if it had never been booked.
+    pub fn record_cancel(&mut self, amount: u64) -> Result<()> {
+        let total_locked = self.total_locked.checked_sub(amount)
+            .ok_or(BridgeError::AccountingUnderflow)?;
+        require!(total_locked >= self.total_released, BridgeError::AccountingUnderflow);
+        self.hot_locked = self.hot_locked.checked_sub(amount)
+            .ok_or(BridgeError::HotEscrowInsufficient)?;
+        self.total_locked = total_locked;
+
+        Ok(())
+    }
+
+    /// Books a wrapped SOL release against the shared `total_released`. Unlike
+    /// `record_release` it leaves `hot_locked` alone, as the funds come from the
+    /// wrapped SOL token escrow.
+    pub fn record_wrapped_release(&mut self, amount: u64) -> Result<()> {
+        let total_released = self.total_released.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+        require!(total_released <= self.total_locked, BridgeError::AccountingUnderflow);
+        self.total_released = total_released;
+
+        Ok(())
+    }
+
+    /// Reward paid to the relayer submitting a signature-verified release of
+    /// `amount`, at `relayer_reward_bps` and rounded down.
+    pub fn relayer_reward(&self, amount: u64) -> Result<u64> {
+        compute_fee(amount, self.relayer_reward_bps)
+    }
+
+    /// Books `amount` as paid out of the hot escrow, refusing to release more than
+    /// was ever locked or than the hot escrow holds. Returns the remaining `net_locked`.
+    pub fn record_release(&mut self, amount: u64) -> Result<u64> {
+        let total_released = self.total_released.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+        require!(total_released <= self.total_locked, BridgeError::AccountingUnderflow);
+        self.hot_locked = self.hot_locked.checked_sub(amount)
+            .ok_or(BridgeError::HotEscrowInsufficient)?;
+        self.total_released = total_released;
+
+        self.net_locked()
+    }
+
+    /// Sets `total_locked` to `new_total_locked`, applying the difference to
+    /// `hot_locked` so the hot/cold split still adds up to `net_locked`.
+    /// Returns the previous `total_locked`.
+    pub fn adjust_total_locked(&mut self, new_total_locked: u64) -> Result<u64> {
+        require!(new_total_locked >= self.total_released, BridgeError::AccountingUnderflow);
+
+        let old_total_locked = self.total_locked;
+        self.hot_locked = if new_total_locked >= old_total_locked {
+            self.hot_locked.checked_add(new_total_locked - old_total_locked)
+                .ok_or(BridgeError::Overflow)?
+        } else {
+            self.hot_locked.checked_sub(old_total_locked - new_total_locked)
+                .ok_or(BridgeError::HotEscrowInsufficient)?
+        };
+        self.total_locked = new_total_locked;
+
+        Ok(old_total_locked)
+    }
+
+    /// Moves `amount` of the locked balance between the hot and cold escrows.
+    pub fn rebalance(&mut self, amount: u64, to_cold: bool) -> Result<()> {
+        if to_cold {
+            self.hot_locked = self.hot_locked.checked_sub(amount)
+                .ok_or(BridgeError::HotEscrowInsufficient)?;
+            self.cold_locked = self.cold_locked.checked_add(amount)
+                .ok_or(BridgeError::Overflow)?;
+        } else {
+            self.cold_locked = self.cold_locked.checked_sub(amount)
+                .ok_or(BridgeError::InsufficientEscrow)?;
+            self.hot_locked = self.hot_locked.checked_add(amount)
+                .ok_or(BridgeError::Overflow)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fee rate for a wallet with `user_volume` lifetime locked, and the applied
+    /// tier: 0 for the base `fee_bps`, otherwise the 1-based index into `fee_tiers`.
+    pub fn fee_for_volume(&self, user_volume: u64) -> (u16, u8) {
+        let mut applied = (self.fee_bps, 0);
+        for (index, tier) in self.fee_tiers.iter().enumerate() {
+            if user_volume >= tier.threshold && tier.fee_bps < applied.0 {
+                applied = (tier.fee_bps, index as u8 + 1);
+            }
+        }
+        applied
+    }
+
+    /// Checks a lock of `amount` lamports against the configured limits, splits
+    /// off the bridge fee for a wallet with `user_volume` lifetime locked and
+    /// books the net amount in the daily window.
+    pub fn prepare_lock(
+        &mut self,
+        amount: u64,
+        user_volume: u64,
+        partner_fee_bps: Option<u16>,
+        now: i64,
+    ) -> Result<LockAmounts> {
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require!(amount >= self.min_lock_amount, BridgeError::BelowMinimum);
+        require!(
+            self.max_lock_amount == 0 || amount <= self.max_lock_amount,
+            BridgeError::AboveMaximum
+        );
+
+        // A partner rate replaces the volume tiers outright
+        let (fee_bps, fee_tier) = match partner_fee_bps {
+            Some(fee_bps) => (fee_bps, 0),
+            None => self.fee_for_volume(user_volume),
+        };
+        let fee = compute_fee(amount, fee_bps)?;
+        // Never book a lock that bridges nothing once the fee is taken
+        require!(amount > fee, BridgeError::AmountBelowFee);
+        let net = amount.checked_sub(fee)
+            .ok_or(BridgeError::Overflow)?;
+        let daily_remaining = self.record_daily_volume(net, now)?;
+
+        Ok(LockAmounts {
+            gross: amount,
+            fee,
+            fee_tier,
+            net,
+            daily_remaining,
+        })
+    }
+
+    /// Books `dest_gas` lamports of prepaid Unicity gas received by the escrow.
+    pub fn record_dest_gas(&mut self, dest_gas: u64) -> Result<()> {
+        self.total_dest_gas = self.total_dest_gas.checked_add(dest_gas)
+            .ok_or(BridgeError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Keys whose signature authorizes a release at `now`: `relayer_pubkey`,
+    /// joined by `next_relayer` during a rotation's grace window and replaced by
+    /// it once the window ends. Empty while no relayer is set.
+    pub fn accepted_relayers(&self, now: i64) -> Vec<Pubkey> {
+        let mut relayers = Vec::with_capacity(2);
+        let rotating = self.next_relayer != Pubkey::default();
+        if self.relayer_pubkey != Pubkey::default()
+            && !(rotating && now >= self.relayer_rotation_eta)
+        {
+            relayers.push(self.relayer_pubkey);
+        }
+        if rotating {
+            relayers.push(self.next_relayer);
+        }
+        relayers
+    }
+
+    /// Whether `key` is an accepted relayer at `now`, see `accepted_relayers`.
+    pub fn is_relayer(&self, key: &Pubkey, now: i64) -> bool {
+        self.accepted_relayers(now).contains(key)
+    }
+
+    /// The `withdraw_splits` slots in use: every split has a non-zero share, so
+    /// they end at the first zeroed one.
+    pub fn configured_withdraw_splits(&self) -> &[WithdrawSplit] {
+        let len = self.withdraw_splits.iter()
+            .position(|split| split.bps == 0)
+            .unwrap_or(MAX_WITHDRAW_SPLITS);
+        &self.withdraw_splits[..len]
+    }
+
+    /// Each `withdraw_splits` recipient's share of an emergency withdraw of
+    /// `amount`, rounded down, with the rounding remainder going to the last one
+    /// so the shares add up to `amount`.
+    pub fn withdraw_split_amounts(&self, amount: u64) -> Result<Vec<u64>> {
+        let splits = self.configured_withdraw_splits();
+        let mut shares = Vec::with_capacity(splits.len());
+        let mut paid: u64 = 0;
+        for split in splits {
+            let share = compute_fee(amount, split.bps)?;
+            paid = paid.checked_add(share)
+                .ok_or(BridgeError::Overflow)?;
+            shares.push(share);
+        }
+        if let Some(last) = shares.last_mut() {
+            *last = last.checked_add(amount.saturating_sub(paid))
+                .ok_or(BridgeError::Overflow)?;
+        }
+
+        Ok(shares)
+    }
+
+    /// Adds a lock of `amount` to `lifetime_volume`.
+    pub fn record_volume(&mut self, amount: u64) -> Result<()> {
+        self.lifetime_volume = self.lifetime_volume.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+        Ok(())
+    }
+
+    /// Hot escrow lamports owed to someone: `hot_locked` plus the prepaid gas
+    /// in `total_dest_gas`. Anything beyond that and the rent reserve is surplus.
+    pub fn hot_escrow_committed(&self) -> u64 {
+        self.hot_locked.saturating_add(self.total_dest_gas)
+    }
+
+    /// Referrer's cut of `lock`'s fee at `referral_bps`, capped at the fee itself,
+    /// which a volume tier or partner rate may have made smaller.
+    pub fn referral_fee(&self, lock: &LockAmounts) -> Result<u64> {
+        Ok(compute_fee(lock.gross, self.referral_bps)?.min(lock.fee))
+    }
+
+    /// Extends the lock hash chain with `lock_id`, returning
+    /// `sha256(previous_head || lock_id)`. The chain starts from 32 zero bytes and
+    /// covers SOL and SPL locks alike, so a relayer that replays the chain notices
+    /// any event it missed.
+    pub fn chain_lock(&mut self, lock_id: &[u8; 32]) -> [u8; 32] {
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(&self.last_sequence_hash);
+        data[32..].copy_from_slice(lock_id);
+        self.last_sequence_hash = hash(&data).to_bytes();
+        self.last_sequence_hash
+    }
+
+    /// Folds `lock_id` into the current epoch's commitment as
+    /// `epoch_root = sha256(epoch_root || lock_id)`, starting from 32 zero bytes.
+    /// Unlike `last_sequence_hash` the fold restarts every epoch, so the Unicity
+    /// side can check a finalized `EpochCommitment` against that epoch's locks alone.
+    pub fn fold_epoch(&mut self, lock_id: &[u8; 32]) -> Result<()> {
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(&self.epoch_root);
+        data[32..].copy_from_slice(lock_id);
+        self.epoch_root = hash(&data).to_bytes();
+        self.epoch_lock_count = self.epoch_lock_count.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Moves on to the next epoch with a zero root and lock count.
+    pub fn roll_epoch(&mut self) -> Result<()> {
+        self.current_epoch = self.current_epoch.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
+        self.epoch_root = [0u8; 32];
+        self.epoch_lock_count = 0;
+
+        Ok(())
+    }
+
+    /// Schedules an emergency withdraw `timelock_secs` after `now`, returning its ETA.
+    pub fn schedule_emergency(&mut self, now: i64) -> Result<i64> {
+        require!(self.emergency_eta == 0, BridgeError::EmergencyAlreadyScheduled);
+        let eta = now.checked_add(self.timelock_secs)
+            .ok_or(BridgeError::Overflow)?;
+        // An ETA of 0 means "nothing scheduled", so never store it as a real one.
+        self.emergency_eta = eta.max(1);
+        self.emergency_nonce = self.nonce;
+
+        Ok(self.emergency_eta)
+    }
+
+    /// Clears a scheduled emergency withdraw that is due at `now`, so each
+    /// schedule authorizes exactly one withdraw. With `emergency_binds_nonce` set,
+    /// a lock since the schedule makes it stale.
+    pub fn consume_emergency_schedule(&mut self, now: i64) -> Result<()> {
+        require!(self.emergency_eta != 0, BridgeError::EmergencyNotScheduled);
+        require!(now >= self.emergency_eta, BridgeError::TimelockNotExpired);
+        require!(
+            !self.emergency_binds_nonce || self.nonce == self.emergency_nonce,
+            BridgeError::EmergencyScheduleStale
+        );
+        self.emergency_eta = 0;
+
+        Ok(())
+    }
+
+    /// Records `now` as the latest lock time, rejecting a validator clock that
+    /// has moved backwards since the previous lock.
+    pub fn advance_lock_clock(&mut self, now: i64) -> Result<i64> {
+        require!(now >= self.last_lock_ts, BridgeError::ClockRegression);
+        self.last_lock_ts = now;
+        Ok(now)
+    }
+
+    /// Adds `amount` to the current daily window, starting a fresh window once
+    /// `DAILY_WINDOW_SECS` have elapsed since the last one began. Returns the
+    /// remaining headroom, or `u64::MAX` when no daily limit is configured.
+    pub fn record_daily_volume(&mut self, amount: u64, now: i64) -> Result<u64> {
+        if now.saturating_sub(self.daily_window_start) >= DAILY_WINDOW_SECS {
+            self.daily_locked = 0;
+            self.daily_window_start = now;
+        }
+
+        let daily_locked = self.daily_locked.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+        if self.daily_limit == 0 {
+            self.daily_locked = daily_locked;
+            return Ok(u64::MAX);
+        }
+
+        require!(daily_locked <= self.daily_limit, BridgeError::DailyLimitExceeded);
+        self.daily_locked = daily_locked;
+
+        Ok(self.daily_limit - daily_locked)
+    }
+
+    /// Counts a lock at `now` towards the velocity window, starting a fresh
+    /// window once `velocity_window_secs` have elapsed since the last one began.
+    /// Sets `paused` and returns true when the lock takes the window past
+    /// `max_locks_per_window`. The lock still lands, since failing it would roll
+    /// the pause back with it; later transactions are refused.
+    pub fn record_lock_velocity(&mut self, now: i64) -> Result<bool> {
+        if self.max_locks_per_window == 0 || self.velocity_window_secs == 0 {
+            return Ok(false);
+        }
+        if now.saturating_sub(self.velocity_window_start) >= self.velocity_window_secs {
+            self.velocity_window_locks = 0;
+            self.velocity_window_start = now;
+        }
+
+        self.velocity_window_locks = self.velocity_window_locks.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
+        let tripped = !self.paused && self.velocity_window_locks > self.max_locks_per_window;
+        if tripped {
+            self.paused = true;
+        }
+
+        Ok(tripped)
+    }
+}
+
+/// Amounts of a single lock after `BridgeState::prepare_lock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockAmounts {
+    /// Amount paid by the user, fee included.
+    pub gross: u64,
+    pub fee: u64,
+    /// Fee tier applied, as returned by `BridgeState::fee_for_volume`.
+    pub fee_tier: u8,
+    /// Amount moved to the escrow and bridged to Unicity.
+    pub net: u64,
+    pub daily_remaining: u64,
+}
+
+/// Return data of the `lock_sol` family.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockOutcome {
+    pub lock_id: [u8; 32],
+    /// `daily_limit - daily_locked` after the lock, `u64::MAX` when uncapped.
+    pub daily_remaining: u64,
+}
+
+impl LockOutcome {
+    fn of(event: &TokenLocked) -> Self {
+        Self {
+            lock_id: event.lock_id,
+            daily_remaining: event.daily_remaining,
+        }
+    }
+}
+
+/// Discounted fee rate for wallets whose lifetime locked volume reaches `threshold`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct FeeTier {
+    pub threshold: u64,
+    pub fee_bps: u16,
+}
+
+/// Why an emergency withdraw was made. The emergency withdraw instructions take
+/// it as its `u8` code, which `EmergencyWithdrawal` carries as `reason_code`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EmergencyReason {
+    /// Responding to an exploit, a compromised key or another live incident.
+    Incident = 0,
+    /// Moving funds to a new escrow or program version.
+    Migration = 1,
+    /// Planned operations such as a drill or topping up cold storage.
+    Routine = 2,
+}
+
+impl EmergencyReason {
+    /// Decodes a `reason_code`, failing with `InvalidReasonCode` on unknown codes.
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0 => Ok(Self::Incident),
+            1 => Ok(Self::Migration),
+            2 => Ok(Self::Routine),
+            _ => err!(BridgeError::InvalidReasonCode),
+        }
+    }
+}
+
+/// A treasury wallet's share of every emergency withdraw, in basis points.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+pub struct WithdrawSplit {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+/// The `TokenLocked` fields `derive_lock_id` hashes besides the user.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockPreimage {
+    pub nonce: u64,
+    pub timestamp: i64,
+    /// Net amount, the event's `amount`.
+    pub amount: u64,
+    pub unicity_recipient: String,
+    pub memo: [u8; 32],
+    pub batch_index: u16,
+}
+
+impl LockPreimage {
+    fn lock_id(&self, user: &Pubkey) -> [u8; 32] {
+        derive_lock_id(
+            user,
+            self.nonce,
+            self.timestamp,
+            self.amount,
+            &self.unicity_recipient,
+            &self.memo,
+            self.batch_index,
+        )
+    }
+}
+
+/// One entry of a `lock_sol_batch` call.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockRequest {
+    pub amount: u64,
+    pub unicity_recipient: String,
+}
+
+/// Per-wallet lock history, created on a user's first lock.
+#[account]
+#[derive(InitSpace, Default)]
+pub struct UserStats {
+    pub total_locked: u64,
+    pub lock_count: u64,
+    pub last_nonce: u64,
+    /// Timestamp of this wallet's most recent lock.
+    pub last_lock_ts: i64,
+}
+
+impl UserStats {
+    /// Rejects a lock at `now` that comes less than `min_interval_secs` after this
+    /// wallet's previous one, then records `now` as its latest lock time.
+    pub fn enforce_cooldown(&mut self, now: i64, min_interval_secs: i64) -> Result<()> {
+        require!(
+            self.lock_count == 0 || now.saturating_sub(self.last_lock_ts) >= min_interval_secs,
+            BridgeError::RateLimited
+        );
+        self.last_lock_ts = now;
+
+        Ok(())
+    }
+}
+
+/// Per-mint accounting for SPL token locks, the token counterpart of
+/// `BridgeState::total_locked` and `total_released`.
+#[account]
+#[derive(InitSpace, Default)]
+pub struct AssetStats {
+    pub mint: Pubkey,
+    pub total_locked: u64,
+    pub total_released: u64,
+}
+
+impl AssetStats {
+    /// Books `amount` as released from this mint's escrow, refusing to release
+    /// more than was ever locked. Returns the remaining locked amount.
+    pub fn record_release(&mut self, amount: u64) -> Result<u64> {
+        let total_released = self.total_released.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+        require!(total_released <= self.total_locked, BridgeError::AccountingUnderflow);
+        self.total_released = total_released;
+
+        Ok(self.total_locked - total_released)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProcessedRelease {
+    pub unicity_tx_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub processed_at: i64,
+}
+
+/// A release waiting in the FIFO queue, seeded by its `release_nonce`.
+#[account]
+#[derive(InitSpace)]
+pub struct QueuedRelease {
+    pub release_nonce: u64,
+    pub unicity_tx_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    /// Paid the entry's rent and gets it back when it is processed.
+    pub payer: Pubkey,
+    pub queued_at: i64,
+}
+
+/// A user's pending request to refund an unprocessed lock, at
+/// `[b"refund", user, lock_id]`.
+#[account]
+#[derive(InitSpace)]
+pub struct RefundRequest {
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    pub requested_at: i64,
+}
+
+/// Terminal state of a lock: either minted on Unicity or refunded on Solana.
+#[account]
+#[derive(InitSpace)]
+pub struct ProcessedLock {
+    pub lock_id: [u8; 32],
+    pub refunded: bool,
+    pub processed_at: i64,
+}
+
+/// Finalized lock commitment of one epoch, at `[b"epoch", epoch]` with the epoch
+/// little-endian.
+#[account]
+#[derive(InitSpace)]
+pub struct EpochCommitment {
+    pub epoch: u64,
+    /// `BridgeState::epoch_root` when the epoch was rolled.
+    pub root: [u8; 32],
+    pub lock_count: u64,
+    pub finalized_at: i64,
+}
+
+/// Running total of SOL bridged to one Unicity recipient, at
+/// `[b"recipient", recipient_key(unicity_recipient)]`. Locks made through
+/// `lock_sol_batch` are not counted.
+#[account]
+#[derive(InitSpace)]
+pub struct RecipientStats {
+    pub recipient_key: [u8; 32],
+    /// Net lamports locked for the recipient.
+    pub total_bridged: u64,
+    pub count: u64,
+}
+
+impl RecipientStats {
+    pub fn record(&mut self, recipient_key: [u8; 32], amount: u64) -> Result<()> {
+        self.recipient_key = recipient_key;
+        self.total_bridged = self.total_bridged.checked_add(amount)
+            .ok_or(BridgeError::Overflow)?;
+        self.count = self.count.checked_add(1)
+            .ok_or(BridgeError::Overflow)?;
+
+        Ok(())
+    }
+}
+
+/// Outcome of a `lock_sol_idempotent` call, keyed by its `client_key`.
+#[account]
+#[derive(InitSpace)]
+pub struct LockReceipt {
+    pub client_key: [u8; 32],
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    /// Net amount held in escrow for the lock.
+    pub amount: u64,
+    /// Bridge fee paid to the fee collector, kept if the lock is cancelled.
+    pub fee: u64,
+    pub locked_at: i64,
+}
+
+/// A user's pending lock made through `lock_sol_with_receipt`. `user` comes
+/// first, so wallets can find a user's receipts with a memcmp at offset 8.
+#[account]
+#[derive(InitSpace)]
+pub struct PendingLock {
+    pub user: Pubkey,
+    pub receipt_key: [u8; 32],
+    pub lock_id: [u8; 32],
+    /// Net amount held in escrow for the lock.
+    pub amount: u64,
+    pub fee: u64,
+    #[max_len(MAX_RECIPIENT_LEN)]
+    pub unicity_recipient: String,
+    pub nonce: u64,
+    pub locked_at: i64,
+}
+
+/// Negotiated fee rate for a partner front-end; see `register_partner`.
+#[account]
+#[derive(InitSpace)]
+pub struct PartnerConfig {
+    pub partner: Pubkey,
+    pub fee_bps: u16,
+}
+
+/// Marks a front-end the admin lets earn referral fees; see `register_referrer`.
+#[account]
+#[derive(InitSpace)]
+pub struct ReferrerConfig {
+    pub referrer: Pubkey,
+    pub registered_at: i64,
+}
+
+/// Marks a wallet the operator has blocked from locking; see `deny_user`.
+#[account]
+#[derive(InitSpace)]
+pub struct DeniedUser {
+    pub user: Pubkey,
+    pub denied_at: i64,
+}
+
+/// The only Unicity recipient a user may lock to while `bindings_required` is
+/// set; see `bind_recipient`.
+#[account]
+#[derive(InitSpace)]
+pub struct RecipientBinding {
+    pub user: Pubkey,
+    /// `recipient_key` of the bound recipient.
+    pub recipient_hash: [u8; 32],
+    pub bound_at: i64,
+}
+
+/// Marks a Unicity recipient the operator has blocked locks to; see `block_recipient`.
+#[account]
+#[derive(InitSpace)]
+pub struct BlockedRecipient {
+    /// `recipient_key` of the blocked address.
+    pub recipient_key: [u8; 32],
+    pub blocked_at: i64,
+}
+
+#[event]
+pub struct BridgeInitialized {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenLocked {
+    pub version: u8,
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    /// Amount bridged to Unicity, net of the bridge fee.
+    pub amount: u64,
+    /// `amount` in Unicity units, see `BridgeState::unicity_amount`.
+    pub unicity_amount: u64,
+    /// Amount debited from the user, including the bridge fee.
+    pub gross_amount: u64,
+    pub fee: u64,
+    /// 0 for the base rate, otherwise the 1-based `BridgeState::fee_tiers` entry applied.
+    pub fee_tier: u8,
+    pub unicity_recipient: String,
+    /// Opaque integrator tag from `lock_sol_with_memo`; zero otherwise.
+    pub memo: [u8; 32],
+    pub nonce: u64,
+    /// Nonce of the previous lock (SOL or SPL), for contiguity checks.
+    pub prev_nonce: u64,
+    /// Lock hash chain head after this lock, see `BridgeState::chain_lock`.
+    pub sequence_hash: [u8; 32],
+    pub timestamp: i64,
+    /// Slot the lock landed in, for confirmation-depth checks.
+    pub slot: u64,
+    /// Remaining daily headroom after this lock, `u64::MAX` when uncapped.
+    pub daily_remaining: u64,
+    /// `BridgeState::total_locked` after this lock.
+    pub total_locked: u64,
+    /// Hot escrow lamports after this lock; indexers can compare it against `hot_locked`.
+    pub escrow_balance: u64,
+    /// Partner whose `PartnerConfig` rate replaced the bridge fee, if any.
+    pub partner: Option<Pubkey>,
+    /// Account that paid the `UserStats` rent; `user` unless the lock was sponsored.
+    pub fee_payer: Pubkey,
+    /// 0-based position within its `lock_sol_batch`; 0 for a single lock.
+    pub batch_index: u16,
+    /// Number of entries in the batch this lock came in; 1 for a single lock.
+    pub batch_size: u16,
+    /// Owner program of `user` when it is a vault PDA, see `lock_sol_from_pda`.
+    pub vault_program: Option<Pubkey>,
+    /// Front-end passed as the lock's referrer, if any.
+    pub referrer: Option<Pubkey>,
+    /// Part of `fee` paid to `referrer`; the fee collector got the rest.
+    pub referral_fee: u64,
+    /// Prepaid Unicity minting gas moved to the escrow on top of `amount`.
+    pub dest_gas: u64,
+    /// `BridgeState::cluster_tag` of the deployment that made the lock.
+    pub cluster_tag: u8,
+}
+
+#[event]
+pub struct SplTokenLocked {
+    pub lock_id: [u8; 32],
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub unicity_recipient: String,
+    pub nonce: u64,
+    pub timestamp: i64,
+    pub prev_nonce: u64,
+    pub sequence_hash: [u8; 32],
+    /// Set for wrapped SOL, which counts towards the bridge-wide `total_locked`.
+    pub native: bool,
+}
+
+#[event]
+pub struct TokenReleased {
+    pub unicity_tx_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    /// `BridgeState::net_locked` after this release.
+    pub net_locked: u64,
+    /// Signer of a signature-verified release, `None` for admin and queued releases.
+    pub relayer: Option<Pubkey>,
+    /// Part of `amount` paid to `relayer`; the recipient gets the rest.
+    pub relayer_reward: u64,
+    /// `BridgeState::cluster_tag` of the deployment that made the release.
+    pub cluster_tag: u8,
+}
+
+#[event]
+pub struct SplTokenReleased {
+    pub unicity_tx_id: [u8; 32],
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    /// `AssetStats` locked minus released for this mint, after this release.
+    pub net_locked: u64,
+}
+
+#[event]
+pub struct ReleaseQueued {
+    pub release_nonce: u64,
+    pub unicity_tx_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReleaseProcessed {
+    pub release_nonce: u64,
+    pub unicity_tx_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct QueuedReleaseCancelled {
+    pub release_nonce: u64,
+    pub unicity_tx_id: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyNonceBindingUpdated {
+    pub emergency_binds_nonce: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawScheduled {
+    /// Earliest time the withdraw can execute.
+    pub eta: i64,
+    /// `BridgeState::nonce` the schedule was taken at.
+    pub nonce: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawCancelled {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawal {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    /// Co-signing guardian, if one is configured.
+    pub guardian: Option<Pubkey>,
+    /// `EmergencyReason` the admin gave for the withdraw.
+    pub reason_code: u8,
+}
+
+/// One split recipient's part of an `EmergencyWithdrawal`.
+#[event]
+pub struct EmergencyWithdrawalSplit {
+    pub recipient: Pubkey,
+    pub bps: u16,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowRebalanced {
+    pub amount: u64,
+    /// True for hot to cold, false for cold to hot.
+    pub to_cold: bool,
+    pub hot_locked: u64,
+    pub cold_locked: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HealthOk {
+    pub net_locked: u64,
+    /// `BridgeState::lifetime_volume`.
+    pub lifetime_volume: u64,
+    pub escrow_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SurplusReconciled {
+    pub surplus: u64,
+    pub fee_collector: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountingAdjusted {
+    pub old_total_locked: u64,
+    pub new_total_locked: u64,
+    /// Opaque operator reference for the intervention, e.g. a ticket hash.
+    pub reason: [u8; 32],
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NonceReset {
+    pub old_nonce: u64,
+    pub new_nonce: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DustSwept {
+    pub amount: u64,
+    pub fee_collector: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DustThresholdUpdated {
+    pub dust_threshold: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralBpsUpdated {
+    pub referral_bps: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferrerUpdated {
+    pub referrer: Pubkey,
+    pub registered: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub treasury: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeesCollected {
+    pub amount: u64,
+    pub treasury: Pubkey,
+    /// `BridgeState::total_fees_collected` after this sweep.
+    pub total_fees_collected: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultProgramUpdated {
+    pub vault_program: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerRewardUpdated {
+    pub relayer_reward_bps: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StateMigrated {
+    /// Size of `bridge_state` before the migration.
+    pub old_space: u64,
+    /// Size of `bridge_state` after the migration, `8 + BridgeState::INIT_SPACE`.
+    pub space: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BridgeClosed {
+    pub admin: Pubkey,
+    /// Lamports swept from the escrow.
+    pub swept: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BridgeKilled {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseStateChanged {
+    pub paused: bool,
+    pub locks_paused: bool,
+    pub releases_paused: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferAccepted {
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LimitsUpdated {
+    pub min_lock_amount: u64,
+    pub max_lock_amount: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DailyLimitUpdated {
+    pub daily_limit: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockVelocityLimitUpdated {
+    pub max_locks_per_window: u32,
+    pub velocity_window_secs: i64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+/// The bridge paused itself after `window_locks` locks within one velocity window.
+#[event]
+pub struct AutoPaused {
+    pub window_locks: u32,
+    pub max_locks_per_window: u32,
+    pub velocity_window_secs: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub fee_bps: u16,
+    pub fee_collector: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerUpdated {
+    pub relayer: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerRotationScheduled {
+    pub relayer: Pubkey,
+    pub next_relayer: Pubkey,
+    pub rotation_eta: i64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ValidatorSetUpdated {
+    pub validators: Vec<Pubkey>,
+    pub threshold: u8,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ValidatorsSigned {
+    pub unicity_tx_id: [u8; 32],
+    pub validators: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct RefundRequested {
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockRefunded {
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PendingLockClosed {
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    /// Whether the lock was refunded or cancelled rather than minted on Unicity.
+    pub refunded: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockCancelled {
+    pub lock_id: [u8; 32],
+    pub user: Pubkey,
+    /// Net amount returned to the user.
+    pub amount: u64,
+    /// Bridge fee taken at lock time, which stays with the fee collector.
+    pub fee_retained: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EpochRolled {
+    pub epoch: u64,
+    pub root: [u8; 32],
+    pub lock_count: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LockProcessed {
+    pub lock_id: [u8; 32],
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundDelayUpdated {
+    pub refund_delay: i64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MinIntervalUpdated {
+    pub min_interval_secs: i64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChecksumRequiredUpdated {
+    pub checksum_required: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecipientRulesUpdated {
+    pub min_len: u16,
+    pub max_len: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DenylistUpdated {
+    pub user: Pubkey,
+    pub denied: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecipientBound {
+    pub user: Pubkey,
+    pub recipient_hash: [u8; 32],
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BindingsRequiredUpdated {
+    pub bindings_required: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RecipientBlocklistUpdated {
+    pub unicity_recipient: String,
+    pub recipient_key: [u8; 32],
+    pub blocked: bool,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeTiersUpdated {
+    pub tiers: Vec<FeeTier>,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawSplitsUpdated {
+    pub splits: Vec<WithdrawSplit>,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PartnerRegistered {
+    pub partner: Pubkey,
+    pub fee_bps: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuardianUpdated {
+    pub guardian: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CancelWindowUpdated {
+    pub cancel_window_secs: i64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnicityDecimalsUpdated {
+    pub unicity_decimals: u8,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TvlCapUpdated {
+    pub tvl_cap: u64,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmitFlagsUpdated {
+    pub emit_flags: u8,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[error_code]
+pub enum BridgeError {
+    #[msg("Invalid amount: must be greater than 0")]
+    InvalidAmount,
+    #[msg("Invalid recipient address")]
+    InvalidRecipient,
+    #[msg("Unauthorized: only admin can perform this action")]
+    Unauthorized,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Insufficient locked balance for release")]
+    InsufficientLocked,
+    #[msg("Release has already been processed")]
+    ReleaseAlreadyProcessed,
+    #[msg("Bridge is paused")]
+    BridgePaused,
+    #[msg("Lock amount is below the configured minimum")]
+    BelowMinimum,
+    #[msg("Lock amount is above the configured maximum")]
+    AboveMaximum,
+    #[msg("Invalid limits: minimum must not exceed maximum")]
+    InvalidLimits,
+    #[msg("Daily lock limit exceeded")]
+    DailyLimitExceeded,
+    #[msg("Fee exceeds the maximum allowed rate")]
+    FeeTooHigh,
+    #[msg("Fee collector does not match the configured account")]
+    InvalidFeeCollector,
+    #[msg("Nothing to withdraw from the escrow")]
+    NothingToWithdraw,
+    #[msg("Insufficient escrow balance")]
+    InsufficientEscrow,
+    #[msg("Relayer has not been configured")]
+    RelayerNotSet,
+    #[msg("Missing or invalid Ed25519 signature verification")]
+    InvalidSignature,
+    #[msg("Validator set has not been configured")]
+    ValidatorSetNotConfigured,
+    #[msg("Too many validators")]
+    TooManyValidators,
+    #[msg("Threshold must not exceed the number of validators")]
+    InvalidThreshold,
+    #[msg("Duplicate validator in set")]
+    DuplicateValidator,
+    #[msg("Signer is not in the validator set")]
+    UnknownValidator,
+    #[msg("Not enough distinct validator signatures")]
+    ThresholdNotMet,
+    #[msg("Clock moved backwards since the previous lock")]
+    ClockRegression,
+    #[msg("Refund delay has not elapsed yet")]
+    RefundNotYetAvailable,
+    #[msg("Refund delay must not be negative")]
+    InvalidRefundDelay,
+    #[msg("Batch has too many entries")]
+    BatchTooLarge,
+    #[msg("Escrow is not the canonical escrow PDA")]
+    InvalidEscrow,
+    #[msg("Released amount would exceed the total locked")]
+    AccountingUnderflow,
+    #[msg("Wallet is locking too frequently")]
+    RateLimited,
+    #[msg("Minimum lock interval must not be negative")]
+    InvalidMinInterval,
+    #[msg("Bridge must be paused")]
+    BridgeNotPaused,
+    #[msg("Locked funds are still outstanding")]
+    OutstandingLocks,
+    #[msg("Recipient length range is invalid")]
+    InvalidRecipientRules,
+    #[msg("Escrow holds less than the outstanding locked amount")]
+    SolvencyViolation,
+    #[msg("Emergency withdraw timelock must not be negative")]
+    InvalidTimelock,
+    #[msg("No emergency withdraw is scheduled")]
+    EmergencyNotScheduled,
+    #[msg("An emergency withdraw is already scheduled")]
+    EmergencyAlreadyScheduled,
+    #[msg("Emergency withdraw timelock has not expired")]
+    TimelockNotExpired,
+    #[msg("Lock deadline has passed")]
+    DeadlineExceeded,
+    #[msg("Wallet is denied by the bridge operator")]
+    UserDenied,
+    #[msg("Hot escrow cannot cover this amount")]
+    HotEscrowInsufficient,
+    #[msg("Too many fee tiers")]
+    TooManyFeeTiers,
+    #[msg("Bridge is already initialized")]
+    AlreadyInitialized,
+    #[msg("Unknown event emission flag")]
+    InvalidEmitFlags,
+    #[msg("Bridge TVL cap reached")]
+    TvlCapReached,
+    #[msg("Guardian signature required")]
+    GuardianRequired,
+    #[msg("Lock amount does not exceed the bridge fee")]
+    AmountBelowFee,
+    #[msg("Cancel window must not be negative")]
+    InvalidCancelWindow,
+    #[msg("Lock can no longer be cancelled")]
+    CancelWindowExpired,
+    #[msg("Lock receipt does not match the lock")]
+    InvalidLockReceipt,
+    #[msg("Unicity decimals out of range")]
+    InvalidDecimals,
+    #[msg("Relayer reward exceeds the maximum")]
+    RelayerRewardTooHigh,
+    #[msg("Locks are paused")]
+    LocksPaused,
+    #[msg("Releases are paused")]
+    ReleasesPaused,
+    #[msg("Unicity recipient is blocked by the bridge operator")]
+    RecipientBlocked,
+    #[msg("Blocked-recipient account does not match the recipient")]
+    InvalidBlocklistAccount,
+    #[msg("Account is not a bridge state")]
+    InvalidStateAccount,
+    #[msg("Bridge state is larger than the current layout")]
+    InvalidStateSize,
+    #[msg("No vault program is registered")]
+    VaultProgramNotSet,
+    #[msg("Vault is not a PDA of the vault program")]
+    InvalidVault,
+    #[msg("The same account was passed in two distinct roles")]
+    AliasedAccounts,
+    #[msg("Bridge has been killed")]
+    BridgeKilled,
+    #[msg("Unicity recipient checksum does not match")]
+    InvalidRecipientChecksum,
+    #[msg("No treasury is set")]
+    TreasuryNotSet,
+    #[msg("Treasury account does not match the configured treasury")]
+    InvalidTreasury,
+    #[msg("Fee vault holds less than the requested amount")]
+    InsufficientFees,
+    #[msg("Escrow surplus is not below the dust threshold")]
+    SurplusAboveDustThreshold,
+    #[msg("Referral rate exceeds the bridge fee")]
+    ReferralTooHigh,
+    #[msg("A lock cannot refer itself")]
+    InvalidReferrer,
+    #[msg("Emergency withdraw was scheduled before the latest lock")]
+    EmergencyScheduleStale,
+    #[msg("Relayer rotation grace period cannot be negative")]
+    InvalidGracePeriod,
+    #[msg("Unknown emergency withdraw reason code")]
+    InvalidReasonCode,
+    #[msg("Lock velocity window cannot be negative")]
+    InvalidVelocityWindow,
+    #[msg("Lock would leave the escrow below its rent-exempt minimum")]
+    EscrowNotRentExempt,
+    #[msg("Recipient does not match the user's binding")]
+    RecipientNotBound,
+    #[msg("Withdraw splits must be distinct, non-zero and sum to 10000 bps")]
+    InvalidWithdrawSplits,
+    #[msg("Remaining accounts do not match the configured withdraw splits")]
+    InvalidWithdrawSplitAccount,
+    #[msg("Lock preimage does not hash to the lock_id for this user")]
+    LockIdMismatch,
+    #[msg("A partner rate needs the partner's signature")]
+    PartnerNotSigner,
+    #[msg("Referrer is not registered")]
+    ReferrerNotRegistered,
+}
+
+/// Off-chain builders for the bridge's most used instructions, deriving every
+/// PDA the way the program's account constraints do. Only compiled with the
+/// `client` feature and never for the on-chain target.
+#[cfg(all(feature = "client", not(target_os = "solana")))]
+pub mod client {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use anchor_lang::{InstructionData, ToAccountMetas};
+
+    pub fn bridge_state_pda(program_id: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"bridge_state"], program_id).0
+    }
+
+    pub fn escrow_pda(program_id: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"escrow"], program_id).0
+    }
+
+    pub fn user_stats_pda(program_id: &Pubkey, user: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"user", user.as_ref()], program_id).0
+    }
+
+    pub fn denied_user_pda(program_id: &Pubkey, user: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"denied", user.as_ref()], program_id).0
+    }
+
+    pub fn recipient_stats_pda(program_id: &Pubkey, unicity_recipient: &str) -> Pubkey {
+        let key = recipient_key(unicity_recipient);
+        Pubkey::find_program_address(&[b"recipient", key.as_ref()], program_id).0
+    }
+
+    pub fn blocked_recipient_pda(program_id: &Pubkey, unicity_recipient: &str) -> Pubkey {
+        let key = recipient_key(unicity_recipient);
+        Pubkey::find_program_address(&[b"blocked_rcpt", key.as_ref()], program_id).0
+    }
+
+    pub fn processed_release_pda(program_id: &Pubkey, unicity_tx_id: &[u8; 32]) -> Pubkey {
+        Pubkey::find_program_address(&[b"processed", unicity_tx_id.as_ref()], program_id).0
+    }
+
+    /// `LockReceipt` a `lock_sol_idempotent` by `user` with `client_key` opens.
+    pub fn lock_receipt_pda(program_id: &Pubkey, user: &Pubkey, client_key: &[u8; 32]) -> Pubkey {
+        Pubkey::find_program_address(&[b"lock", user.as_ref(), client_key.as_ref()], program_id).0
+    }
+
+    /// Authority `emit_cpi!` signs its event self-CPI with.
+    pub fn event_authority_pda(program_id: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"__event_authority"], program_id).0
+    }
+
+    /// `lock_sol` of `amount` lamports from `user` to `unicity_recipient`, without
+    /// a deadline, partner or referrer. `fee_collector` must be `BridgeState::fee_collector`.
+    pub fn lock_sol_ix(
+        program_id: &Pubkey,
+        user: &Pubkey,
+        fee_collector: &Pubkey,
+        amount: u64,
+        unicity_recipient: &str,
+    ) -> Instruction {
+        let accounts = crate::accounts::LockSol {
+            bridge_state: bridge_state_pda(program_id),
+            escrow: escrow_pda(program_id),
+            fee_collector: *fee_collector,
+            user_stats: user_stats_pda(program_id, user),
+            denied_user: denied_user_pda(program_id, user),
+            blocked_recipient: blocked_recipient_pda(program_id, unicity_recipient),
+            recipient_stats: recipient_stats_pda(program_id, unicity_recipient),
+            partner_config: None,
+            partner: None,
+            referrer: None,
+            referrer_config: None,
+            recipient_binding: None,
+            user: *user,
+            system_program: System::id(),
+            event_authority: event_authority_pda(program_id),
+            program: *program_id,
+        };
+        let data = crate::instruction::LockSol {
+            amount,
+            unicity_recipient: unicity_recipient.to_string(),
+            deadline: 0,
+            dest_gas: 0,
+        };
+
+        Instruction {
+            program_id: *program_id,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// Admin `release_sol` of `amount` lamports to `recipient` for `unicity_tx_id`.
+    pub fn release_sol_ix(
+        program_id: &Pubkey,
+        admin: &Pubkey,
+        recipient: &Pubkey,
+        amount: u64,
+        unicity_tx_id: [u8; 32],
+    ) -> Instruction {
+        let accounts = crate::accounts::ReleaseSol {
+            bridge_state: bridge_state_pda(program_id),
+            processed_release: processed_release_pda(program_id, &unicity_tx_id),
+            escrow: escrow_pda(program_id),
+            recipient: *recipient,
+            admin: *admin,
+            system_program: System::id(),
+        };
+        let data = crate::instruction::ReleaseSol {
+            amount,
+            recipient: *recipient,
+            unicity_tx_id,
+        };
+
+        Instruction {
+            program_id: *program_id,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    /// A freshly created, still all-zero `bridge_state`.
+    fn state() -> BridgeState {
+        let data = vec![0u8; BridgeState::INIT_SPACE];
+        BridgeState::deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    /// `BridgeState` as laid out before `rotate_relayer`, when `reserved` was down
+    /// to 26 bytes. Kept frozen so migrations are tested against a real layout.
+    #[derive(AnchorSerialize, Default, InitSpace)]
+    struct LegacyBridgeState {
+        admin: Pubkey,
+        total_locked: u64,
+        nonce: u64,
+        paused: bool,
+        pending_admin: Option<Pubkey>,
+        min_lock_amount: u64,
+        max_lock_amount: u64,
+        daily_limit: u64,
+        daily_locked: u64,
+        daily_window_start: i64,
+        fee_bps: u16,
+        fee_collector: Pubkey,
+        relayer_pubkey: Pubkey,
+        #[max_len(MAX_VALIDATORS)]
+        validators: Vec<Pubkey>,
+        threshold: u8,
+        bridge_bump: u8,
+        escrow_bump: u8,
+        last_lock_ts: i64,
+        refund_delay: i64,
+        total_released: u64,
+        last_sequence_hash: [u8; 32],
+        min_interval_secs: i64,
+        recipient_min_len: u16,
+        recipient_max_len: u16,
+        timelock_secs: i64,
+        emergency_eta: i64,
+        hot_locked: u64,
+        cold_locked: u64,
+        #[max_len(MAX_FEE_TIERS)]
+        fee_tiers: Vec<FeeTier>,
+        release_nonce: u64,
+        next_release_to_process: u64,
+        emit_flags: u8,
+        tvl_cap: u64,
+        guardian: Pubkey,
+        cancel_window_secs: i64,
+        unicity_decimals: u8,
+        relayer_reward_bps: u16,
+        locks_paused: bool,
+        releases_paused: bool,
+        current_epoch: u64,
+        epoch_root: [u8; 32],
+        epoch_lock_count: u64,
+        vault_program: Pubkey,
+        cluster_tag: u8,
+        killed: bool,
+        checksum_required: bool,
+        treasury: Pubkey,
+        total_fees_collected: u64,
+        dust_threshold: u64,
+        referral_bps: u16,
+        emergency_nonce: u64,
+        emergency_binds_nonce: bool,
+        total_dest_gas: u64,
+        reserved: [u8; 26],
+    }
+
+    fn error_code(err: Error) -> u32 {
+        match err {
+            Error::AnchorError(err) => err.error_code_number,
+            Error::ProgramError(err) => panic!("unexpected program error: {err}"),
+        }
+    }
+
+    #[test]
+    fn unicity_address_accepts_hex_of_the_expected_length() {
+        let address = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        let len = UNICITY_ADDRESS_LEN;
+        assert!(validate_unicity_address(address, len, len).is_ok());
+        assert!(validate_unicity_address(&address.to_uppercase(), len, len).is_ok());
+    }
+
+    #[test]
+    fn recipient_rules_come_from_state() {
+        let mut state = state();
+        state.recipient_min_len = 40;
+        state.recipient_max_len = 66;
+
+        assert!(state.validate_recipient(&"a".repeat(40)).is_ok());
+        assert!(state.validate_recipient(&"A".repeat(66)).is_ok());
+        for len in [39, 67] {
+            assert_eq!(
+                error_code(state.validate_recipient(&"a".repeat(len)).unwrap_err()),
+                u32::from(BridgeError::InvalidRecipient)
+            );
+        }
+    }
+
+    #[test]
+    fn recipient_length_is_capped_at_the_hard_limit() {
+        let at_limit = "a".repeat(MAX_RECIPIENT_LEN);
+        let over_limit = "a".repeat(MAX_RECIPIENT_LEN + 1);
+
+        assert!(validate_unicity_address(&at_limit, 1, MAX_RECIPIENT_LEN).is_ok());
+        for max_len in [MAX_RECIPIENT_LEN, usize::MAX] {
+            assert_eq!(
+                error_code(validate_unicity_address(&over_limit, 1, max_len).unwrap_err()),
+                u32::from(BridgeError::InvalidRecipient)
+            );
+        }
+    }
+
+    #[test]
+    fn recipient_length_counts_bytes_not_characters() {
+        let mut state = state();
+        state.recipient_min_len = UNICITY_ADDRESS_LEN as u16;
+        state.recipient_max_len = UNICITY_ADDRESS_LEN as u16;
+
+        // 32 two-byte characters: 64 bytes, so within the length range by byte.
+        let multi_byte = "é".repeat(UNICITY_ADDRESS_LEN / 2);
+        assert_eq!(multi_byte.len(), UNICITY_ADDRESS_LEN);
+        assert_eq!(
+            error_code(state.validate_recipient(&multi_byte).unwrap_err()),
+            u32::from(BridgeError::InvalidRecipient)
+        );
+
+        let at_max = "Ab".repeat(UNICITY_ADDRESS_LEN / 2);
+        assert!(state.validate_recipient(&at_max).is_ok());
+        assert!(state.validate_recipient(&at_max[1..]).is_err());
+        // Events and accounts Borsh-encode it behind a u32 byte length.
+        let encoded = at_max.try_to_vec().unwrap();
+        assert_eq!(encoded[..4], (UNICITY_ADDRESS_LEN as u32).to_le_bytes());
+        assert_eq!(String::try_from_slice(&encoded).unwrap(), at_max);
+    }
+
+    #[test]
+    fn unicity_address_rejects_malformed_input() {
+        let address = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        let invalid = [
+            String::new(),
+            address[..63].to_string(),
+            format!("{address}0"),
+            format!("[SHA256]{address}"),
+            format!("{}g", &address[..63]),
+            format!("{} ", &address[..63]),
+        ];
+
+        for s in &invalid {
+            assert_eq!(
+                error_code(
+                    validate_unicity_address(s, UNICITY_ADDRESS_LEN, UNICITY_ADDRESS_LEN).unwrap_err()
+                ),
+                u32::from(BridgeError::InvalidRecipient),
+                "{s:?}"
+            );
+        }
+    }
+
+    /// Builds Ed25519 program instruction data with self-referencing offsets.
+    fn ed25519_data(entries: &[([u8; 32], [u8; 64], &[u8])]) -> Vec<u8> {
+        let mut data = vec![entries.len() as u8, 0];
+        let mut payload = Vec::new();
+        let payload_start = 2 + entries.len() * ED25519_OFFSETS_LEN;
+        for (pubkey, signature, message) in entries {
+            let pubkey_offset = payload_start + payload.len();
+            payload.extend_from_slice(pubkey);
+            let signature_offset = payload_start + payload.len();
+            payload.extend_from_slice(signature);
+            let message_offset = payload_start + payload.len();
+            payload.extend_from_slice(message);
+            for value in [
+                signature_offset,
+                u16::MAX as usize,
+                pubkey_offset,
+                u16::MAX as usize,
+                message_offset,
+                message.len(),
+                u16::MAX as usize,
+            ] {
+                data.extend_from_slice(&(value as u16).to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&payload);
+        data
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn client_lock_sol_ix_lists_the_lock_sol_accounts() {
+        use anchor_lang::{Discriminator, InstructionData};
+
+        let user = Pubkey::new_unique();
+        let fee_collector = Pubkey::new_unique();
+        let recipient = "15ED2F7f97c6E98C15d8Dc4bA8beF3eBEFC5Ebf049Dab7cdD075d334a6bBA2F9";
+        let ix = client::lock_sol_ix(&ID, &user, &fee_collector, 42, recipient);
+
+        let find = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &ID).0;
+        let recipient_key = recipient_key(recipient);
+        let expected = [
+            (find(&[b"bridge_state"]), true, false),
+            (find(&[b"escrow"]), true, false),
+            (fee_collector, true, false),
+            (find(&[b"user", user.as_ref()]), true, false),
+            (find(&[b"denied", user.as_ref()]), false, false),
+            (find(&[b"blocked_rcpt", recipient_key.as_ref()]), false, false),
+            (find(&[b"recipient", recipient_key.as_ref()]), true, false),
+            // An absent `partner_config`, `partner`, `referrer`,
+            // `referrer_config` or `recipient_binding` is passed as the program id
+            (ID, false, false),
+            (ID, false, false),
+            (ID, false, false),
+            (ID, false, false),
+            (ID, false, false),
+            (user, true, true),
+            (System::id(), false, false),
+            (find(&[b"__event_authority"]), false, false),
+            (ID, false, false),
+        ];
+        let metas: Vec<_> = ix
+            .accounts
+            .iter()
+            .map(|meta| (meta.pubkey, meta.is_writable, meta.is_signer))
+            .collect();
+        assert_eq!(metas, expected);
+
+        assert_eq!(ix.program_id, ID);
+        assert!(ix.data.starts_with(instruction::LockSol::DISCRIMINATOR));
+        let data = instruction::LockSol {
+            amount: 42,
+            unicity_recipient: recipient.to_string(),
+            deadline: 0,
+            dest_gas: 0,
+        };
+        assert_eq!(ix.data, data.data());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn client_release_sol_ix_uses_the_replay_guard_of_its_tx_id() {
+        let (admin, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let unicity_tx_id = [7u8; 32];
+        let ix = client::release_sol_ix(&ID, &admin, &recipient, 42, unicity_tx_id);
+
+        let processed = Pubkey::find_program_address(&[b"processed", &unicity_tx_id], &ID).0;
+        let keys: Vec<_> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(
+            keys,
+            [
+                client::bridge_state_pda(&ID),
+                processed,
+                client::escrow_pda(&ID),
+                recipient,
+                admin,
+                System::id(),
+            ]
+        );
+        let signers: Vec<_> = ix.accounts.iter().filter(|meta| meta.is_signer).collect();
+        assert_eq!(signers.len(), 1);
+        assert_eq!(signers[0].pubkey, admin);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn client_lock_receipt_pda_is_scoped_to_the_user() {
+        let (user, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let client_key = [7u8; 32];
+        let receipt = client::lock_receipt_pda(&ID, &user, &client_key);
+
+        let seeds: &[&[u8]] = &[b"lock", user.as_ref(), &client_key];
+        assert_eq!(receipt, Pubkey::find_program_address(seeds, &ID).0);
+        assert_ne!(receipt, client::lock_receipt_pda(&ID, &other, &client_key));
+    }
+
+    #[test]
+    fn recipient_key_ignores_hex_case() {
+        let address = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        assert_eq!(recipient_key(address), recipient_key(&address.to_uppercase()));
+        assert_eq!(recipient_key(address), hash(address.as_bytes()).to_bytes());
+        assert_ne!(recipient_key(address), recipient_key(&address[1..]));
+    }
+
+    #[test]
+    fn release_message_has_the_documented_layout() {
+        let recipient = Pubkey::new_from_array([0xab; 32]);
+        let message = release_message(0x0102_0304_0506_0708, &recipient, &[0xcd; 32]);
+
+        let mut expected = vec![0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01];
+        expected.extend_from_slice(&[0xab; 32]);
+        expected.extend_from_slice(&[0xcd; 32]);
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn ed25519_instruction_yields_every_signature() {
+        let data = ed25519_data(&[([1; 32], [2; 64], b"first"), ([3; 32], [4; 64], b"second")]);
+
+        let entries = parse_ed25519_instruction(&data).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], (&[1u8; 32][..], &[2u8; 64][..], &b"first"[..]));
+        assert_eq!(entries[1], (&[3u8; 32][..], &[4u8; 64][..], &b"second"[..]));
+    }
+
+    #[test]
+    fn ed25519_instruction_rejects_foreign_offsets() {
+        let mut data = ed25519_data(&[([1; 32], [2; 64], b"message")]);
+        // Point the public key at another instruction's data.
+        data[8..10].copy_from_slice(&0u16.to_le_bytes());
+        assert!(parse_ed25519_instruction(&data).is_err());
+
+        let data = ed25519_data(&[([1; 32], [2; 64], b"message")]);
+        assert!(parse_ed25519_instruction(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn fee_rounds_down_and_handles_large_amounts() {
+        assert_eq!(compute_fee(10_000, 25).unwrap(), 25);
+        assert_eq!(compute_fee(399, 25).unwrap(), 0);
+        assert_eq!(compute_fee(u64::MAX, MAX_FEE_BPS).unwrap(), u64::MAX / 10);
+        assert_eq!(compute_fee(1_000, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn lock_id_commits_to_amount_recipient_memo_and_batch_index() {
+        let user = Pubkey::new_unique();
+        let recipient = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        let other = "25ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        let memo = [0u8; 32];
+        let lock_id = derive_lock_id(&user, 1, NOW, 100, recipient, &memo, 0);
+
+        assert_ne!(lock_id, derive_lock_id(&user, 1, NOW, 101, recipient, &memo, 0));
+        assert_ne!(lock_id, derive_lock_id(&user, 1, NOW, 100, other, &memo, 0));
+        assert_ne!(lock_id, derive_lock_id(&user, 1, NOW, 100, recipient, &[1u8; 32], 0));
+        assert_ne!(lock_id, derive_lock_id(&user, 1, NOW, 100, recipient, &memo, 1));
+        assert_eq!(lock_id, derive_lock_id(&user, 1, NOW, 100, recipient, &memo, 0));
+    }
+
+    #[test]
+    fn lock_preimage_only_matches_the_locking_user() {
+        let user = Pubkey::new_unique();
+        let lock = LockPreimage {
+            nonce: 1,
+            timestamp: NOW,
+            amount: 100,
+            unicity_recipient: "alice".to_string(),
+            memo: [7u8; 32],
+            batch_index: 2,
+        };
+        let lock_id = derive_lock_id(&user, 1, NOW, 100, "alice", &[7u8; 32], 2);
+
+        assert_eq!(lock.lock_id(&user), lock_id);
+        assert_ne!(lock.lock_id(&Pubkey::new_unique()), lock_id);
+    }
+
+    #[test]
+    fn prepare_lock_splits_fee_and_books_net_amount() {
+        let mut state = state();
+        state.fee_bps = 100;
+        state.daily_limit = 1_000;
+
+        let lock = state.prepare_lock(500, 0, None, NOW).unwrap();
+        assert_eq!(
+            lock,
+            LockAmounts { gross: 500, fee: 5, fee_tier: 0, net: 495, daily_remaining: 505 }
+        );
+        assert_eq!(state.daily_locked, 495);
+
+        state.min_lock_amount = 10;
+        state.max_lock_amount = 600;
+        for (amount, err) in [
+            (0, BridgeError::InvalidAmount),
+            (9, BridgeError::BelowMinimum),
+            (601, BridgeError::AboveMaximum),
+        ] {
+            assert_eq!(
+                error_code(state.prepare_lock(amount, 0, None, NOW).unwrap_err()),
+                u32::from(err)
+            );
+        }
+    }
+
+    #[test]
+    fn escrow_helper_returns_the_canonical_bump() {
+        let (escrow, bump) = Pubkey::find_program_address(&[b"escrow"], &crate::ID);
+        assert_eq!(assert_escrow(&crate::ID, &escrow).unwrap(), bump);
+
+        let (cold_escrow, _) = Pubkey::find_program_address(&[b"escrow_cold"], &crate::ID);
+        for key in [cold_escrow, Pubkey::new_unique()] {
+            assert_eq!(
+                error_code(assert_escrow(&crate::ID, &key).unwrap_err()),
+                u32::from(BridgeError::InvalidEscrow)
+            );
+        }
+    }
+
+    #[test]
+    fn escrow_must_be_the_system_owned_pda() {
+        let (escrow, _) = Pubkey::find_program_address(&[b"escrow"], &crate::ID);
+        let other = Pubkey::new_unique();
+        let system = System::id();
+        let check = |key: &Pubkey, owner: &Pubkey| {
+            let (mut lamports, mut data) = (0u64, [0u8; 0]);
+            let info = AccountInfo::new(key, false, true, &mut lamports, &mut data, owner, false, 0);
+            assert_canonical_escrow(&info, &crate::ID)
+        };
+
+        assert!(check(&escrow, &system).is_ok());
+        for (key, owner) in [(&other, &system), (&escrow, &crate::ID)] {
+            assert_eq!(
+                error_code(check(key, owner).unwrap_err()),
+                u32::from(BridgeError::InvalidEscrow)
+            );
+        }
+    }
+
+    #[test]
+    fn releases_never_exceed_total_locked() {
+        let mut state = state();
+        state.total_locked = 100;
+        state.hot_locked = 100;
+
+        assert_eq!(state.record_release(60).unwrap(), 40);
+        assert_eq!(state.record_release(40).unwrap(), 0);
+        assert_eq!(
+            error_code(state.record_release(1).unwrap_err()),
+            u32::from(BridgeError::AccountingUnderflow)
+        );
+        assert_eq!(state.total_released, 100);
+        assert_eq!(state.net_locked().unwrap(), 0);
+    }
+
+    #[test]
+    fn lock_hash_chain_links_each_lock_to_the_previous_head() {
+        let mut state = state();
+        let (first, second) = ([1u8; 32], [2u8; 32]);
+
+        let head = state.chain_lock(&first);
+        assert_eq!(head, hash(&[[0u8; 32], first].concat()).to_bytes());
+        let next = state.chain_lock(&second);
+        assert_eq!(next, hash(&[head, second].concat()).to_bytes());
+        assert_eq!(state.last_sequence_hash, next);
+    }
+
+    #[test]
+    fn epoch_root_folds_locks_and_restarts_on_roll() {
+        let mut state = state();
+        let (first, second) = ([1u8; 32], [2u8; 32]);
+
+        state.fold_epoch(&first).unwrap();
+        let root = hash(&[[0u8; 32], first].concat()).to_bytes();
+        assert_eq!(state.epoch_root, root);
+        state.fold_epoch(&second).unwrap();
+        assert_eq!(state.epoch_root, hash(&[root, second].concat()).to_bytes());
+        assert_eq!(state.epoch_lock_count, 2);
+
+        state.roll_epoch().unwrap();
+        assert_eq!(state.current_epoch, 1);
+        assert_eq!((state.epoch_root, state.epoch_lock_count), ([0u8; 32], 0));
+        state.fold_epoch(&first).unwrap();
+        assert_eq!(state.epoch_root, root);
+    }
+
+    #[test]
+    fn user_cooldown_rejects_locks_inside_the_interval() {
+        let mut stats = UserStats::default();
+
+        stats.enforce_cooldown(NOW, 60).unwrap();
+        stats.lock_count = 1;
+        assert_eq!(
+            error_code(stats.enforce_cooldown(NOW + 59, 60).unwrap_err()),
+            u32::from(BridgeError::RateLimited)
+        );
+        assert_eq!(stats.last_lock_ts, NOW);
+        stats.enforce_cooldown(NOW + 60, 60).unwrap();
+        assert_eq!(stats.last_lock_ts, NOW + 60);
+
+        // 0 disables the cooldown.
+        stats.enforce_cooldown(NOW + 60, 0).unwrap();
+    }
+
+    #[test]
+    fn solvency_requires_hot_escrow_to_cover_hot_locked() {
+        let mut state = state();
+        state.hot_locked = 70;
+        state.cold_locked = 500;
+
+        assert!(state.check_solvency(70).is_ok());
+        assert_eq!(
+            error_code(state.check_solvency(69).unwrap_err()),
+            u32::from(BridgeError::SolvencyViolation)
+        );
+    }
+
+    #[test]
+    fn releases_are_funded_from_the_hot_escrow_only() {
+        let mut state = state();
+        state.total_locked = 100;
+        state.hot_locked = 100;
+
+        state.rebalance(80, true).unwrap();
+        assert_eq!((state.hot_locked, state.cold_locked), (20, 80));
+        assert_eq!(
+            error_code(state.record_release(21).unwrap_err()),
+            u32::from(BridgeError::HotEscrowInsufficient)
+        );
+        assert_eq!(state.record_release(20).unwrap(), 80);
+
+        assert_eq!(
+            error_code(state.rebalance(81, false).unwrap_err()),
+            u32::from(BridgeError::InsufficientEscrow)
+        );
+        state.rebalance(80, false).unwrap();
+        assert_eq!((state.hot_locked, state.cold_locked), (80, 0));
+    }
+
+    #[test]
+    fn accounting_adjustments_keep_the_hot_split_consistent() {
+        let mut state = state();
+        state.total_locked = 100;
+        state.total_released = 40;
+        state.hot_locked = 50;
+        state.cold_locked = 10;
+
+        assert_eq!(state.adjust_total_locked(120).unwrap(), 100);
+        assert_eq!(state.hot_locked, 70);
+        assert_eq!(state.adjust_total_locked(60).unwrap(), 120);
+        assert_eq!((state.hot_locked, state.net_locked().unwrap()), (10, 20));
+        assert_eq!(
+            error_code(state.adjust_total_locked(39).unwrap_err()),
+            u32::from(BridgeError::AccountingUnderflow)
+        );
+    }
+
+    #[test]
+    fn asset_releases_never_exceed_the_mint_total() {
+        let mut stats = AssetStats { total_locked: 50, ..AssetStats::default() };
+
+        assert_eq!(stats.record_release(50).unwrap(), 0);
+        assert_eq!(
+            error_code(stats.record_release(1).unwrap_err()),
+            u32::from(BridgeError::AccountingUnderflow)
+        );
+    }
+
+    #[test]
+    fn emergency_withdraw_waits_for_the_timelock() {
+        let mut state = state();
+        state.timelock_secs = 3_600;
+
+        assert_eq!(
+            error_code(state.consume_emergency_schedule(NOW).unwrap_err()),
+            u32::from(BridgeError::EmergencyNotScheduled)
+        );
+        assert_eq!(state.schedule_emergency(NOW).unwrap(), NOW + 3_600);
+        assert_eq!(
+            error_code(state.schedule_emergency(NOW).unwrap_err()),
+            u32::from(BridgeError::EmergencyAlreadyScheduled)
+        );
+        assert_eq!(
+            error_code(state.consume_emergency_schedule(NOW + 3_599).unwrap_err()),
+            u32::from(BridgeError::TimelockNotExpired)
+        );
+        state.consume_emergency_schedule(NOW + 3_600).unwrap();
+        assert_eq!(state.emergency_eta, 0);
+    }
+
+    #[test]
+    fn bound_emergency_schedule_goes_stale_after_a_lock() {
+        let mut state = state();
+        state.nonce = 5;
+        state.schedule_emergency(NOW).unwrap();
+        assert_eq!(state.emergency_nonce, 5);
+
+        // Unbound, a lock since the schedule does not matter
+        state.nonce = 6;
+        state.consume_emergency_schedule(NOW).unwrap();
+
+        state.emergency_binds_nonce = true;
+        state.schedule_emergency(NOW).unwrap();
+        state.consume_emergency_schedule(NOW).unwrap();
+        state.schedule_emergency(NOW).unwrap();
+        state.nonce = 7;
+        assert_eq!(
+            error_code(state.consume_emergency_schedule(NOW).unwrap_err()),
+            u32::from(BridgeError::EmergencyScheduleStale)
+        );
+        assert_ne!(state.emergency_eta, 0);
+    }
+
+    #[test]
+    fn referrers_must_be_registered() {
+        let referrer = Pubkey::new_unique();
+        let config = ReferrerConfig { referrer, registered_at: NOW };
+        let other = ReferrerConfig { referrer: Pubkey::new_unique(), registered_at: NOW };
+
+        assert!(check_referrer_registered(&referrer, Some(&config)).is_ok());
+        for referrer_config in [None, Some(&other)] {
+            assert_eq!(
+                error_code(check_referrer_registered(&referrer, referrer_config).unwrap_err()),
+                u32::from(BridgeError::ReferrerNotRegistered)
+            );
+        }
+    }
+
+    #[test]
+    fn lock_deadline_expires_after_its_timestamp() {
+        assert!(check_deadline(NOW, NOW).is_ok());
+        assert!(check_deadline(0, NOW).is_ok());
+        assert_eq!(
+            error_code(check_deadline(NOW - 1, NOW).unwrap_err()),
+            u32::from(BridgeError::DeadlineExceeded)
+        );
+    }
+
+    #[test]
+    fn fee_tiers_apply_the_lowest_rate_reached() {
+        let mut state = state();
+        state.fee_bps = 100;
+        state.fee_tiers = vec![
+            FeeTier { threshold: 1_000, fee_bps: 50 },
+            FeeTier { threshold: 10_000, fee_bps: 20 },
+            FeeTier { threshold: 5_000, fee_bps: 80 },
+        ];
+
+        assert_eq!(state.fee_for_volume(999), (100, 0));
+        assert_eq!(state.fee_for_volume(1_000), (50, 1));
+        assert_eq!(state.fee_for_volume(5_000), (50, 1));
+        assert_eq!(state.fee_for_volume(10_000), (20, 2));
+
+        let lock = state.prepare_lock(1_000, 10_000, None, NOW).unwrap();
+        assert_eq!((lock.fee, lock.fee_tier), (2, 2));
+    }
+
+    #[test]
+    fn lock_must_bridge_something_after_the_fee() {
+        // Rates this high cannot be configured; the guard backs up `MAX_FEE_BPS`.
+        let mut state = state();
+        assert_eq!(
+            error_code(state.prepare_lock(10, 0, Some(10_000), NOW).unwrap_err()),
+            u32::from(BridgeError::AmountBelowFee)
+        );
+
+        let lock = state.prepare_lock(10, 0, Some(9_000), NOW).unwrap();
+        assert_eq!((lock.fee, lock.net), (9, 1));
+    }
+
+    #[test]
+    fn partner_rate_replaces_base_and_tiered_fees() {
+        let mut state = state();
+        state.fee_bps = 100;
+        state.fee_tiers = vec![FeeTier { threshold: 0, fee_bps: 50 }];
+
+        let lock = state.prepare_lock(10_000, 0, Some(250), NOW).unwrap();
+        assert_eq!((lock.fee, lock.fee_tier, lock.net), (250, 0, 9_750));
+        let lock = state.prepare_lock(10_000, 0, Some(0), NOW).unwrap();
+        assert_eq!((lock.fee, lock.net), (0, 10_000));
+    }
+
+    #[test]
+    fn invariants_hold_for_a_consistent_state() {
+        let mut state = state();
+        assert_eq!(state.check_invariants(0, 0, 890).unwrap(), 0);
+
+        state.total_locked = 1_000;
+        state.total_released = 400;
+        state.hot_locked = 500;
+        state.cold_locked = 100;
+        state.validators = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        state.threshold = 2;
+        state.fee_bps = MAX_FEE_BPS;
+        assert_eq!(state.check_invariants(1_390, 990, 890).unwrap(), 600);
+    }
+
+    #[test]
+    fn invariants_report_each_corruption() {
+        let healthy = || {
+            let mut state = state();
+            state.total_locked = 1_000;
+            state.hot_locked = 1_000;
+            state
+        };
+        let check = |state: &BridgeState, hot_lamports| {
+            error_code(state.check_invariants(hot_lamports, 0, 890).unwrap_err())
+        };
+
+        let mut state = healthy();
+        state.total_released = 1_001;
+        assert_eq!(check(&state, 1_890), u32::from(BridgeError::AccountingUnderflow));
+
+        // Short by a lamport of the rent reserve.
+        assert_eq!(check(&healthy(), 1_889), u32::from(BridgeError::SolvencyViolation));
+
+        let mut state = healthy();
+        state.cold_locked = 1;
+        assert_eq!(check(&state, 1_890), u32::from(BridgeError::SolvencyViolation));
+
+        let mut state = healthy();
+        state.threshold = 1;
+        assert_eq!(check(&state, 1_890), u32::from(BridgeError::InvalidThreshold));
+
+        let mut state = healthy();
+        state.fee_tiers = vec![FeeTier { threshold: 0, fee_bps: MAX_FEE_BPS + 1 }];
+        assert_eq!(check(&state, 1_890), u32::from(BridgeError::FeeTooHigh));
+    }
+
+    #[test]
+    fn cancel_unbooks_the_lock() {
+        let mut state = state();
+        state.total_locked = 1_000;
+        state.total_released = 300;
+        state.hot_locked = 600;
+
+        state.record_cancel(500).unwrap();
+        assert_eq!((state.total_locked, state.hot_locked), (500, 100));
+        // Never below what was already released, nor below the hot escrow share.
+        assert_eq!(
+            error_code(state.record_cancel(201).unwrap_err()),
+            u32::from(BridgeError::AccountingUnderflow)
+        );
+        assert_eq!(
+            error_code(state.record_cancel(101).unwrap_err()),
+            u32::from(BridgeError::HotEscrowInsufficient)
+        );
+    }
+
+    #[test]
+    fn wrapped_release_leaves_hot_escrow_accounting_alone() {
+        let mut state = state();
+        state.total_locked = 1_000;
+        state.hot_locked = 600;
+
+        state.record_wrapped_release(400).unwrap();
+        assert_eq!((state.total_released, state.hot_locked), (400, 600));
+        assert_eq!(
+            error_code(state.record_wrapped_release(601).unwrap_err()),
+            u32::from(BridgeError::AccountingUnderflow)
+        );
+    }
+
+    #[test]
+    fn guardian_is_only_required_once_set() {
+        let mut state = state();
+        assert_eq!(state.check_guardian(None).unwrap(), None);
+
+        let guardian = Pubkey::new_unique();
+        state.guardian = guardian;
+        assert_eq!(state.check_guardian(Some(guardian)).unwrap(), Some(guardian));
+        for signer in [None, Some(Pubkey::new_unique())] {
+            assert_eq!(
+                error_code(state.check_guardian(signer).unwrap_err()),
+                u32::from(BridgeError::GuardianRequired)
+            );
+        }
+    }
+
+    #[test]
+    fn lock_headroom_rejects_overflowing_totals() {
+        let mut state = state();
+        state.total_locked = u64::MAX - 10;
+        state.hot_locked = 5;
+        assert!(state.check_lock_headroom(10).is_ok());
+        assert_eq!(
+            error_code(state.check_lock_headroom(11).unwrap_err()),
+            u32::from(BridgeError::Overflow)
+        );
+
+        state.total_locked = 5;
+        state.hot_locked = u64::MAX;
+        assert_eq!(
+            error_code(state.check_lock_headroom(1).unwrap_err()),
+            u32::from(BridgeError::Overflow)
+        );
+        assert_eq!((state.total_locked, state.hot_locked), (5, u64::MAX));
+    }
+
+    #[test]
+    fn relayer_reward_is_a_rounded_down_share_of_the_release() {
+        let mut state = state();
+        assert_eq!(state.relayer_reward(1_000_000).unwrap(), 0);
+
+        state.relayer_reward_bps = MAX_RELAYER_REWARD_BPS;
+        assert_eq!(state.relayer_reward(1_000_000).unwrap(), 10_000);
+        assert_eq!(state.relayer_reward(99).unwrap(), 0);
+        assert_eq!(state.relayer_reward(u64::MAX).unwrap(), u64::MAX / 100);
+    }
+
+    #[test]
+    fn pause_flags_gate_each_direction() {
+        let code = |result: Result<()>| result.err().map(error_code);
+        let mut state = state();
+        let combinations = [(false, false), (true, false), (false, true), (true, true)];
+        for (locks_paused, releases_paused) in combinations {
+            state.locks_paused = locks_paused;
+            state.releases_paused = releases_paused;
+            assert_eq!(
+                code(state.check_locks_open()),
+                locks_paused.then(|| u32::from(BridgeError::LocksPaused))
+            );
+            assert_eq!(
+                code(state.check_releases_open()),
+                releases_paused.then(|| u32::from(BridgeError::ReleasesPaused))
+            );
+        }
+
+        state.paused = true;
+        state.locks_paused = false;
+        state.releases_paused = false;
+        assert_eq!(code(state.check_locks_open()), Some(u32::from(BridgeError::BridgePaused)));
+        assert_eq!(code(state.check_releases_open()), Some(u32::from(BridgeError::BridgePaused)));
+    }
+
+    #[test]
+    fn recipient_checksum_catches_a_flipped_case() {
+        let checksummed = "15ED2F7f97c6E98C15d8Dc4bA8beF3eBEFC5Ebf049Dab7cdD075d334a6bBA2F9";
+        let code = |result: Result<()>| result.err().map(error_code);
+        let mut state = state();
+
+        assert!(verify_recipient_checksum(checksummed).is_ok());
+        let corrupted = checksummed.replacen("ED", "eD", 1);
+        assert_eq!(
+            code(verify_recipient_checksum(&corrupted)),
+            Some(u32::from(BridgeError::InvalidRecipientChecksum))
+        );
+        let lowercase = checksummed.to_ascii_lowercase();
+        assert!(verify_recipient_checksum(&lowercase).is_err());
+
+        assert!(state.check_recipient_checksum(&lowercase).is_ok());
+        state.checksum_required = true;
+        assert!(state.check_recipient_checksum(checksummed).is_ok());
+        assert_eq!(
+            code(state.check_recipient_checksum(&lowercase)),
+            Some(u32::from(BridgeError::InvalidRecipientChecksum))
+        );
+    }
+
+    #[test]
+    fn killed_bridge_refuses_locks_but_not_releases() {
+        let code = |result: Result<()>| result.err().map(error_code);
+        let mut state = state();
+        state.killed = true;
+
+        assert_eq!(code(state.check_locks_open()), Some(u32::from(BridgeError::BridgeKilled)));
+        assert!(state.check_releases_open().is_ok());
+
+        state.paused = true;
+        assert_eq!(code(state.check_locks_open()), Some(u32::from(BridgeError::BridgeKilled)));
+    }
+
+    #[test]
+    fn lock_id_prehashes_recipients_of_any_length() {
+        let user = Pubkey::new_unique();
+        let memo = [0u8; 32];
+        let derive = |recipient: &str| derive_lock_id(&user, 1, NOW, 100, recipient, &memo, 0);
+
+        let mut long = "a".repeat(MAX_RECIPIENT_LEN);
+        let lock_id = derive(&long);
+        assert_eq!(lock_id, derive(&long));
+        long.replace_range(MAX_RECIPIENT_LEN - 1.., "b");
+        assert_ne!(lock_id, derive(&long));
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&user.to_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&NOW.to_le_bytes());
+        data.extend_from_slice(&100u64.to_le_bytes());
+        data.extend_from_slice(&hash(b"ab").to_bytes());
+        data.extend_from_slice(&memo);
+        data.extend_from_slice(&0u16.to_le_bytes());
+        assert_eq!(data.len(), LOCK_ID_PREIMAGE_LEN);
+        assert_eq!(derive("ab"), hash(&data).to_bytes());
+    }
+
+    #[test]
+    fn blocked_recipient_account_must_match_and_be_empty() {
+        let recipient = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        let seeds: &[&[u8]] = &[b"blocked_rcpt", &recipient_key(recipient)];
+        let (key, _) = Pubkey::find_program_address(seeds, &ID);
+        let other = Pubkey::new_unique();
+        let (mut lamports, mut other_lamports) = (0u64, 0u64);
+        let (mut empty, mut other_data, mut blocked) = ([0u8; 0], [0u8; 0], [0u8; 48]);
+        let owner = System::id();
+        let account = |key, lamports, data| {
+            AccountInfo::new(key, false, false, lamports, data, &owner, false, 0)
+        };
+
+        let unblocked = account(&key, &mut lamports, &mut empty[..]);
+        assert!(check_recipient_not_blocked(&ID, &unblocked, recipient).is_ok());
+        assert!(check_recipient_not_blocked(&ID, &unblocked, &recipient.to_uppercase()).is_ok());
+
+        let wrong = account(&other, &mut other_lamports, &mut other_data[..]);
+        assert_eq!(
+            error_code(check_recipient_not_blocked(&ID, &wrong, recipient).unwrap_err()),
+            u32::from(BridgeError::InvalidBlocklistAccount)
+        );
+
+        let mut blocked_lamports = 1u64;
+        let blocked = account(&key, &mut blocked_lamports, &mut blocked[..]);
+        assert_eq!(
+            error_code(check_recipient_not_blocked(&ID, &blocked, recipient).unwrap_err()),
+            u32::from(BridgeError::RecipientBlocked)
+        );
+    }
+
+    #[test]
+    fn bridge_state_fills_its_space_and_decodes_reserved_bytes() {
+        let mut full = state();
+        full.pending_admin = Some(Pubkey::new_unique());
+        full.validators = vec![Pubkey::new_unique(); MAX_VALIDATORS];
+        full.fee_tiers = vec![FeeTier::default(); MAX_FEE_TIERS];
+        let mut data = Vec::new();
+        full.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + BridgeState::INIT_SPACE);
+
+        // Bytes a later build carves out of `reserved` decode from an older account
+        let reserved_at = data.len() - STATE_RESERVED_LEN;
+        data[reserved_at..].fill(0xff);
+        let decoded = BridgeState::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(decoded.reserved, [0xff; STATE_RESERVED_LEN]);
+        assert_eq!(decoded.validators.len(), MAX_VALIDATORS);
+    }
+
+    #[test]
+    fn undersized_state_is_authorized_and_decodes_once_grown() {
+        let legacy = LegacyBridgeState {
+            admin: Pubkey::new_unique(),
+            total_locked: 7,
+            pending_admin: Some(Pubkey::new_unique()),
+            validators: vec![Pubkey::new_unique(); MAX_VALIDATORS],
+            fee_tiers: vec![FeeTier::default(); MAX_FEE_TIERS],
+            current_epoch: 3,
+            total_dest_gas: 5,
+            ..Default::default()
+        };
+        let mut data = BridgeState::DISCRIMINATOR.to_vec();
+        legacy.serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + LegacyBridgeState::INIT_SPACE);
+        assert!(BridgeState::try_deserialize(&mut data.as_slice()).is_err());
+        assert_eq!(state_admin(&data).unwrap(), legacy.admin);
+
+        // What `migrate_state` leaves behind
+        data.resize(8 + BridgeState::INIT_SPACE, 0);
+        let migrated = BridgeState::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!((migrated.admin, migrated.total_locked), (legacy.admin, 7));
+        assert_eq!(migrated.validators, legacy.validators);
+        assert_eq!((migrated.current_epoch, migrated.total_dest_gas), (3, 5));
+        assert_eq!((migrated.next_relayer, migrated.lifetime_volume), (Pubkey::default(), 0));
+        assert!(migrated.configured_withdraw_splits().is_empty());
+
+        data[0] ^= 1;
+        assert_eq!(
+            error_code(state_admin(&data).unwrap_err()),
+            u32::from(BridgeError::InvalidStateAccount)
+        );
+    }
+
+    #[test]
+    fn vault_must_derive_from_the_vault_program() {
+        let vault_program = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let (vault, bump) =
+            Pubkey::find_program_address(&[b"vault", owner.as_ref()], &vault_program);
+        let seeds = vec![b"vault".to_vec(), owner.to_bytes().to_vec(), vec![bump]];
+        let code = |result: Result<()>| result.err().map(error_code);
+
+        assert!(check_vault(&vault_program, &seeds, &vault).is_ok());
+        assert_eq!(
+            code(check_vault(&Pubkey::default(), &seeds, &vault)),
+            Some(u32::from(BridgeError::VaultProgramNotSet))
+        );
+        assert_eq!(
+            code(check_vault(&Pubkey::new_unique(), &seeds, &vault)),
+            Some(u32::from(BridgeError::InvalidVault))
+        );
+        assert_eq!(
+            code(check_vault(&vault_program, &seeds[..2], &vault)),
+            Some(u32::from(BridgeError::InvalidVault))
+        );
+    }
+
+    #[test]
+    fn emergency_withdraw_rejects_an_admin_aliasing_another_account() {
+        let (admin, escrow, bridge_state) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let code = |result: Result<()>| result.err().map(error_code);
+
+        assert!(check_not_aliased(&admin, &escrow, &bridge_state).is_ok());
+        assert_eq!(
+            code(check_not_aliased(&escrow, &escrow, &bridge_state)),
+            Some(u32::from(BridgeError::AliasedAccounts))
+        );
+        assert_eq!(
+            code(check_not_aliased(&bridge_state, &escrow, &bridge_state)),
+            Some(u32::from(BridgeError::AliasedAccounts))
+        );
+    }
+
+    #[test]
+    fn tvl_cap_bounds_net_locked() {
+        let mut state = state();
+        state.total_locked = 1_000;
+        state.total_released = 200;
+        assert!(state.check_tvl_cap().is_ok());
+
+        state.tvl_cap = 800;
+        assert!(state.check_tvl_cap().is_ok());
+        state.total_locked = 1_001;
+        assert_eq!(
+            error_code(state.check_tvl_cap().unwrap_err()),
+            u32::from(BridgeError::TvlCapReached)
+        );
+        state.total_released = 201;
+        assert!(state.check_tvl_cap().is_ok());
+    }
+
+    #[test]
+    fn unicity_amount_scales_by_decimal_difference() {
+        let mut state = state();
+        state.unicity_decimals = SOL_DECIMALS;
+        assert_eq!(state.unicity_amount(1_500_000_000).unwrap(), 1_500_000_000);
+
+        state.unicity_decimals = 12;
+        assert_eq!(state.unicity_amount(1_500_000_000).unwrap(), 1_500_000_000_000);
+
+        state.unicity_decimals = 6;
+        assert_eq!(state.unicity_amount(1_500_000_999).unwrap(), 1_500_000);
+    }
+
+    #[test]
+    fn unicity_amount_rejects_scale_up_overflow() {
+        let mut state = state();
+        state.unicity_decimals = MAX_UNICITY_DECIMALS;
+        assert_eq!(state.unicity_amount(18_000_000_000).unwrap(), 18_000_000_000_000_000_000);
+        assert_eq!(
+            error_code(state.unicity_amount(u64::MAX / 1_000_000_000 + 1).unwrap_err()),
+            u32::from(BridgeError::Overflow)
+        );
+    }
+
+    #[test]
+    fn escrow_surplus_excludes_rent_and_locked_funds() {
+        assert_eq!(escrow_surplus(1_500, 890, 500), 110);
+        assert_eq!(escrow_surplus(1_390, 890, 500), 0);
+        // An underfunded escrow has no surplus rather than a wrapped one.
+        assert_eq!(escrow_surplus(1_000, 890, 500), 0);
+    }
+
+    #[test]
+    fn collectable_fees_keep_the_vault_rent_exempt() {
+        let code = |result: Result<u64>| result.map_err(error_code);
+
+        assert_eq!(code(collectable_fees(1_500, 890, 0)), Ok(610));
+        assert_eq!(code(collectable_fees(1_500, 890, 600)), Ok(600));
+        assert_eq!(
+            code(collectable_fees(1_500, 890, 611)),
+            Err(u32::from(BridgeError::InsufficientFees))
+        );
+        assert_eq!(
+            code(collectable_fees(890, 890, 0)),
+            Err(u32::from(BridgeError::NothingToWithdraw))
+        );
+    }
+
+    #[test]
+    fn only_a_surplus_below_the_dust_threshold_is_sweepable() {
+        let code = |result: Result<u64>| result.map_err(error_code);
+
+        assert_eq!(code(sweepable_dust(99, 100)), Ok(99));
+        assert_eq!(
+            code(sweepable_dust(100, 100)),
+            Err(u32::from(BridgeError::SurplusAboveDustThreshold))
+        );
+        assert_eq!(
+            code(sweepable_dust(1, 0)),
+            Err(u32::from(BridgeError::SurplusAboveDustThreshold))
+        );
+        assert_eq!(code(sweepable_dust(0, 100)), Err(u32::from(BridgeError::NothingToWithdraw)));
+    }
+
+    #[test]
+    fn referral_fee_never_exceeds_the_lock_fee() {
+        let mut state = state();
+        state.referral_bps = 50;
+        let lock = |fee| LockAmounts {
+            gross: 10_000,
+            fee,
+            fee_tier: 0,
+            net: 10_000 - fee,
+            daily_remaining: 0,
+        };
+
+        assert_eq!(state.referral_fee(&lock(100)).unwrap(), 50);
+        // A cheaper tier or partner rate caps the cut at the fee actually paid
+        assert_eq!(state.referral_fee(&lock(20)).unwrap(), 20);
+        state.referral_bps = 0;
+        assert_eq!(state.referral_fee(&lock(100)).unwrap(), 0);
+    }
+
+    #[test]
+    fn withdraw_splits_add_up_and_leave_the_remainder_to_the_last() {
+        let split = |bps| WithdrawSplit { recipient: Pubkey::new_unique(), bps };
+        let code = |result: Result<()>| result.err().map(error_code);
+        let invalid = Some(u32::from(BridgeError::InvalidWithdrawSplits));
+
+        let mut state = state();
+        assert!(state.withdraw_split_amounts(1_000).unwrap().is_empty());
+        state.withdraw_splits[..2].copy_from_slice(&[split(7_000), split(3_000)]);
+        assert_eq!(state.configured_withdraw_splits(), &state.withdraw_splits[..2]);
+        assert_eq!(code(validate_withdraw_splits(state.configured_withdraw_splits())), None);
+        assert_eq!(state.withdraw_split_amounts(1_000).unwrap(), vec![700, 300]);
+        assert_eq!(state.withdraw_split_amounts(1_001).unwrap(), vec![700, 301]);
+
+        assert_eq!(code(validate_withdraw_splits(&[])), None);
+        assert_eq!(code(validate_withdraw_splits(&[split(7_000), split(2_999)])), invalid);
+        assert_eq!(code(validate_withdraw_splits(&[split(10_000), split(0)])), invalid);
+        let twice = split(5_000);
+        assert_eq!(code(validate_withdraw_splits(&[twice, twice])), invalid);
+        let too_many: Vec<_> = (0..=MAX_WITHDRAW_SPLITS).map(|_| split(2_000)).collect();
+        assert_eq!(code(validate_withdraw_splits(&too_many)), invalid);
+    }
+
+    #[test]
+    fn recipient_binding_is_enforced_only_when_required() {
+        let mut state = state();
+        let bound = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+        let other = "cd".repeat(32);
+        let binding = RecipientBinding {
+            user: Pubkey::new_unique(),
+            recipient_hash: recipient_key(bound),
+            bound_at: NOW,
+        };
+        let code = |result: Result<()>| result.err().map(error_code);
+        let not_bound = Some(u32::from(BridgeError::RecipientNotBound));
+
+        assert_eq!(code(state.check_recipient_binding(None, &other)), None);
+        state.bindings_required = true;
+        assert_eq!(code(state.check_recipient_binding(Some(&binding), bound)), None);
+        assert_eq!(
+            code(state.check_recipient_binding(Some(&binding), &bound.to_uppercase())),
+            None
+        );
+        assert_eq!(code(state.check_recipient_binding(Some(&binding), &other)), not_bound);
+        assert_eq!(code(state.check_recipient_binding(None, bound)), not_bound);
+    }
+
+    #[test]
+    fn dust_lock_into_a_fresh_escrow_must_reach_rent_exemption() {
+        let rent_minimum = 890_880;
+        let code = |result: Result<()>| result.err().map(error_code);
+
+        assert_eq!(
+            code(check_rent_exempt(1_000, rent_minimum)),
+            Some(u32::from(BridgeError::EscrowNotRentExempt))
+        );
+        assert_eq!(code(check_rent_exempt(rent_minimum, rent_minimum)), None);
+        assert_eq!(code(check_rent_exempt(rent_minimum + 1_000, rent_minimum)), None);
+    }
+
+    #[test]
+    fn lifetime_volume_survives_releases_and_adjustments() {
+        let mut state = state();
+        state.total_locked = 1_000;
+        state.hot_locked = 1_000;
+        state.record_volume(1_000).unwrap();
+        state.record_release(400).unwrap();
+        state.adjust_total_locked(600).unwrap();
+
+        assert_eq!(state.net_locked().unwrap(), 200);
+        assert_eq!(state.lifetime_volume, 1_000);
+        state.record_volume(50).unwrap();
+        assert_eq!(state.lifetime_volume, 1_050);
+    }
+
+    #[test]
+    fn lock_velocity_trips_the_pause_once_the_window_overflows() {
+        let mut state = state();
+        assert!(!state.record_lock_velocity(NOW).unwrap());
+        assert_eq!(state.velocity_window_locks, 0);
+
+        state.max_locks_per_window = 2;
+        state.velocity_window_secs = 60;
+        assert!(!state.record_lock_velocity(NOW).unwrap());
+        assert!(!state.record_lock_velocity(NOW + 59).unwrap());
+        // A new window starts the count over
+        assert!(!state.record_lock_velocity(NOW + 60).unwrap());
+        assert!(!state.record_lock_velocity(NOW + 61).unwrap());
+        assert!(!state.paused);
+
+        assert!(state.record_lock_velocity(NOW + 62).unwrap());
+        assert!(state.paused);
+        assert_eq!(state.velocity_window_locks, 3);
+        assert_eq!(
+            state.check_locks_open().err().map(error_code),
+            Some(u32::from(BridgeError::BridgePaused))
+        );
+    }
+
+    #[test]
+    fn emergency_reason_codes_round_trip() {
+        for reason in [
+            EmergencyReason::Incident,
+            EmergencyReason::Migration,
+            EmergencyReason::Routine,
+        ] {
+            assert_eq!(EmergencyReason::from_code(reason as u8).unwrap(), reason);
+        }
+        assert_eq!(
+            error_code(EmergencyReason::from_code(3).unwrap_err()),
+            u32::from(BridgeError::InvalidReasonCode)
+        );
+    }
+
+    #[test]
+    fn relayer_rotation_accepts_both_keys_until_the_grace_window_ends() {
+        let mut state = state();
+        assert!(state.accepted_relayers(NOW).is_empty());
+
+        let old = Pubkey::new_unique();
+        let new = Pubkey::new_unique();
+        state.relayer_pubkey = old;
+        assert_eq!(state.accepted_relayers(NOW), vec![old]);
+
+        state.next_relayer = new;
+        state.relayer_rotation_eta = NOW + 60;
+        assert_eq!(state.accepted_relayers(NOW + 59), vec![old, new]);
+        assert!(state.is_relayer(&old, NOW + 59));
+        assert!(!state.is_relayer(&old, NOW + 60));
+        assert!(state.is_relayer(&new, NOW + 60));
+        assert!(!state.is_relayer(&Pubkey::new_unique(), NOW));
+    }
+
+    #[test]
+    fn prepaid_dest_gas_is_committed_but_not_locked() {
+        let mut state = state();
+        state.hot_locked = 500;
+        state.record_dest_gas(30).unwrap();
+        state.record_dest_gas(20).unwrap();
+
+        assert_eq!(state.total_dest_gas, 50);
+        assert_eq!(state.hot_escrow_committed(), 550);
+        assert_eq!(escrow_surplus(1_450, 890, state.hot_escrow_committed()), 10);
+        assert_eq!(state.net_locked().unwrap(), 0);
+        assert_eq!(
+            error_code(state.record_dest_gas(u64::MAX).unwrap_err()),
+            u32::from(BridgeError::Overflow)
+        );
+    }
+
+    #[test]
+    fn lock_clock_is_monotonic() {
+        let mut state = state();
+
+        assert_eq!(state.advance_lock_clock(NOW).unwrap(), NOW);
+        assert_eq!(state.advance_lock_clock(NOW).unwrap(), NOW);
+        assert_eq!(
+            error_code(state.advance_lock_clock(NOW - 1).unwrap_err()),
+            u32::from(BridgeError::ClockRegression)
+        );
+        assert_eq!(state.last_lock_ts, NOW);
+    }
+
+    #[test]
+    fn daily_volume_is_capped_within_a_window() {
+        let mut state = state();
+        state.daily_limit = 100;
+
+        assert_eq!(state.record_daily_volume(60, NOW).unwrap(), 40);
+        assert_eq!(state.record_daily_volume(40, NOW + DAILY_WINDOW_SECS - 1).unwrap(), 0);
+        assert_eq!(
+            error_code(state.record_daily_volume(1, NOW + DAILY_WINDOW_SECS - 1).unwrap_err()),
+            u32::from(BridgeError::DailyLimitExceeded)
+        );
+        assert_eq!(state.daily_locked, 100);
+    }
+
+    #[test]
+    fn daily_volume_resets_when_the_window_rolls_over() {
+        let mut state = state();
+        state.daily_limit = 100;
+
+        state.record_daily_volume(100, NOW).unwrap();
+        assert_eq!(state.record_daily_volume(30, NOW + DAILY_WINDOW_SECS).unwrap(), 70);
+        assert_eq!(state.daily_window_start, NOW + DAILY_WINDOW_SECS);
+        assert_eq!(state.daily_locked, 30);
+    }
+
+    #[test]
+    fn daily_volume_is_uncapped_without_a_limit() {
+        let mut state = state();
+
+        assert_eq!(state.record_daily_volume(u64::MAX, NOW).unwrap(), u64::MAX);
+    }
 }
@@ -0,0 +1,72 @@
+//! Runs a lock and its release through the real runtime, where paying out of
+//! the system-owned escrow only works as a transfer signed with its seeds.
+
+use anchor_lang::{
+    prelude::{AccountInfo, Pubkey},
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction, system_program},
+    InstructionData, ToAccountMetas,
+};
+use solana_program_test::{processor, ProgramTest};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use unicity_bridge::client;
+
+const RECIPIENT: &str = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+
+// `processor!` wants independent lifetimes for the account slice and its
+// infos, which Anchor's `entry` ties together.
+fn entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    unicity_bridge::entry(program_id, accounts, data)
+}
+
+#[tokio::test]
+async fn release_sol_pays_out_of_the_escrow() {
+    let program_id = unicity_bridge::ID;
+    let test = ProgramTest::new("unicity_bridge", program_id, processor!(entry));
+    let (banks, admin, blockhash) = test.start().await;
+    let send = |ix: Instruction| {
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&admin.pubkey()),
+            &[&admin],
+            blockhash,
+        );
+        banks.process_transaction(tx)
+    };
+
+    let initialize = Instruction {
+        program_id,
+        accounts: unicity_bridge::accounts::Initialize {
+            bridge_state: client::bridge_state_pda(&program_id),
+            user: admin.pubkey(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: unicity_bridge::instruction::Initialize {
+            admin: admin.pubkey(),
+            timelock_secs: 0,
+            cluster_tag: 0,
+        }
+        .data(),
+    };
+    send(initialize).await.unwrap();
+
+    let amount = LAMPORTS_PER_SOL / 10;
+    let lock =
+        client::lock_sol_ix(&program_id, &admin.pubkey(), &admin.pubkey(), amount, RECIPIENT);
+    send(lock).await.unwrap();
+
+    let escrow = client::escrow_pda(&program_id);
+    let escrow_before = banks.get_balance(escrow).await.unwrap();
+    let recipient = Keypair::new().pubkey();
+    let release =
+        client::release_sol_ix(&program_id, &admin.pubkey(), &recipient, amount, [7u8; 32]);
+    send(release).await.unwrap();
+
+    assert_eq!(banks.get_balance(recipient).await.unwrap(), amount);
+    assert_eq!(banks.get_balance(escrow).await.unwrap(), escrow_before - amount);
+}
@@ -46,9 +46,15 @@ export class SolanaBridgeClient {
   }
 
   /**
-   * Initialize the bridge contract
+   * Initialize the bridge contract. `timelockSecs` is the fixed delay between
+   * scheduling and executing an emergency withdraw. `clusterTag` identifies this
+   * deployment in lock and release events.
    */
-  async initializeBridge(admin: PublicKey): Promise<string> {
+  async initializeBridge(
+    admin: PublicKey,
+    timelockSecs: number,
+    clusterTag = 0
+  ): Promise<string> {
     const [bridgeStatePda] = PublicKey.findProgramAddressSync(
       [Buffer.from("bridge_state")],
       this.program.programId
@@ -56,7 +62,7 @@ export class SolanaBridgeClient {
 
     try {
       const tx = await this.program.methods
-        .initialize(admin)
+        .initialize(admin, new BN(timelockSecs), clusterTag)
         .accountsPartial({
           bridgeState: bridgeStatePda,
           user: this.provider.wallet.publicKey,
@@ -73,9 +79,16 @@ export class SolanaBridgeClient {
   }
 
   /**
-   * Lock SOL in the bridge
+   * Lock SOL in the bridge. A non-zero `deadline` (unix seconds) makes the lock
+   * fail if it lands later. `destGas` lamports are prepaid for minting gas on
+   * Unicity, on top of `amount`.
    */
-  async lockSol(amount: number, unicityRecipient: string): Promise<string> {
+  async lockSol(
+    amount: number,
+    unicityRecipient: string,
+    deadline = 0,
+    destGas = 0
+  ): Promise<string> {
     const [bridgeStatePda] = PublicKey.findProgramAddressSync(
       [Buffer.from("bridge_state")],
       this.program.programId
@@ -86,14 +99,31 @@ export class SolanaBridgeClient {
       this.program.programId
     );
 
+    // Seeded by `recipient_key` in the program: sha256 of the lowercased address
+    const recipientKey = crypto.createHash("sha256")
+      .update(unicityRecipient.toLowerCase())
+      .digest();
+    const [recipientStatsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("recipient"), recipientKey],
+      this.program.programId
+    );
+    const [blockedRecipientPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("blocked_rcpt"), recipientKey],
+      this.program.programId
+    );
+
     const amountLamports = new BN(amount);
 
     try {
+      const bridgeState = await this.program.account.bridgeState.fetch(bridgeStatePda);
       const tx = await this.program.methods
-        .lockSol(amountLamports, unicityRecipient)
+        .lockSol(amountLamports, unicityRecipient, new BN(deadline), new BN(destGas))
         .accountsPartial({
           bridgeState: bridgeStatePda,
           escrow: escrowPda,
+          feeCollector: bridgeState.feeCollector,
+          recipientStats: recipientStatsPda,
+          blockedRecipient: blockedRecipientPda,
           user: this.provider.wallet.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -117,9 +147,10 @@ export class SolanaBridgeClient {
     this.connection.onLogs(
       this.program.programId,
       async (logs, ctx) => {
-        // Look for TokenLocked event in logs
+        // Look for a lock instruction; the TokenLocked event itself travels in a
+        // self-CPI and is decoded from the full transaction below
         const tokenLockedLog = logs.logs.find(log =>
-          log.includes("TokenLocked") || log.includes("Program log:")
+          log.includes("Instruction: LockSol") || log.includes("Program log:")
         );
 
         if (tokenLockedLog && logs.signature) {
@@ -163,11 +194,10 @@ export class SolanaBridgeClient {
         return null;
       }
 
-      // Try to parse events using Anchor's event parser
+      // TokenLocked is emitted through a self-CPI (`emit_cpi!`), so it is decoded
+      // from the inner instructions rather than the program logs
       try {
-        const eventParser = new anchor.EventParser(this.program.programId, this.program.coder);
-        const eventsGenerator = eventParser.parseLogs(txDetails.meta.logMessages || []);
-        const events = Array.from(eventsGenerator);
+        const events = this.parseCpiEvents(txDetails);
 
         // Find TokenLocked event
         const tokenLockedEvent = events.find(event => event.name === 'TokenLocked' || event.name === 'tokenLocked');
@@ -194,6 +224,25 @@ export class SolanaBridgeClient {
     return null;
   }
 
+  /**
+   * Decode the `emit_cpi!` events in a transaction's inner instructions
+   */
+  private parseCpiEvents(txDetails: anchor.web3.VersionedTransactionResponse) {
+    // Anchor's `EVENT_IX_TAG_LE`, prefixing the data of every event self-CPI
+    const eventIxTag = Buffer.from([0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d]);
+    const accountKeys = txDetails.transaction.message.getAccountKeys({
+      accountKeysFromLookups: txDetails.meta?.loadedAddresses
+    });
+
+    return (txDetails.meta?.innerInstructions || [])
+      .flatMap(inner => inner.instructions)
+      .filter(ix => accountKeys.get(ix.programIdIndex)?.equals(this.program.programId))
+      .map(ix => Buffer.from(anchor.utils.bytes.bs58.decode(ix.data)))
+      .filter(data => data.subarray(0, 8).equals(eventIxTag))
+      .map(data => this.program.coder.events.decode(data.subarray(8).toString("base64")))
+      .filter(event => event !== null);
+  }
+
   /**
    * Generate proof for a lock event using Solana's signature status validation
    */
@@ -1,5 +1,17 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import {
+  Ed25519Program,
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import BN from "bn.js";
+import { expect } from "chai";
+import { createHash } from "crypto";
 import { UnicityBridge } from "../target/types/unicity_bridge";
 
 describe("unicity-bridge", () => {
@@ -7,12 +19,1888 @@ describe("unicity-bridge", () => {
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.unicityBridge as Program<UnicityBridge>;
+  const provider = anchor.AnchorProvider.env();
+  const admin = provider.wallet.publicKey;
+
+  const [bridgeStatePda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("bridge_state")],
+    program.programId
+  );
+  const [escrowPda] = PublicKey.findProgramAddressSync([Buffer.from("escrow")], program.programId);
+  const fetchState = () => program.account.bridgeState.fetch(bridgeStatePda);
+
+  // Mirrors `EVENT_VERSION` in the program.
+  const EVENT_VERSION = 16;
+  // Codes of `EmergencyReason` in the program.
+  const INCIDENT = 0;
+  const ROUTINE = 2;
+  // Tag this deployment is initialized with, see `BridgeState::cluster_tag`.
+  const CLUSTER_TAG = 2;
+
+  const unicityRecipient = "15ed2f7f97c6e98c15d8dc4ba8bef3ebefc5ebf049dab7cdd075d334a6bba2f9";
+  // Mirrors `recipient_key` in the program: sha256 of the lowercased address.
+  const recipientPda = (prefix: string, recipient: string) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from(prefix), createHash("sha256").update(recipient.toLowerCase()).digest()],
+      program.programId
+    )[0];
+  const recipientStatsPda = (recipient: string) => recipientPda("recipient", recipient);
+  const blockedRecipientPda = (recipient: string) => recipientPda("blocked_rcpt", recipient);
+  const recipientStats = recipientStatsPda(unicityRecipient);
+  const blockedRecipient = blockedRecipientPda(unicityRecipient);
+  // `LockReceipt` of a `lock_sol_idempotent` by `user` with `clientKey`.
+  const lockReceiptPda = (user: PublicKey, clientKey: number[]) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lock"), user.toBuffer(), Buffer.from(clientKey)],
+      program.programId
+    )[0];
+
+  // Awaits a transaction that must fail and matches the error or its program logs.
+  const expectFailure = async (tx: Promise<unknown>, pattern: RegExp) => {
+    try {
+      await tx;
+    } catch (err) {
+      const logs = (err.logs ?? []).join("\n");
+      expect(`${err}\n${logs}`).to.match(pattern);
+      return;
+    }
+    expect.fail(`expected transaction to fail with ${pattern}`);
+  };
+
+  // Locks `amount` lamports from the provider wallet.
+  const lockSol = (amount: BN, feeCollector: PublicKey = admin, deadline = new BN(0)) =>
+    program.methods
+      .lockSol(amount, unicityRecipient, deadline, new BN(0))
+      .accountsPartial({ user: admin, feeCollector, recipientStats, blockedRecipient })
+      .rpc({ commitment: "confirmed" });
+
+  // Anchor's `EVENT_IX_TAG_LE`, prefixing the data of `emit_cpi!` self-CPIs.
+  const EVENT_IX_TAG = Buffer.from([0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d]);
+
+  // Decodes the `emit_cpi!` events carried by a transaction's inner instructions.
+  const cpiEventsOf = (tx: anchor.web3.VersionedTransactionResponse) => {
+    const accountKeys = tx.transaction.message.getAccountKeys({
+      accountKeysFromLookups: tx.meta.loadedAddresses,
+    });
+    return (tx.meta.innerInstructions ?? [])
+      .flatMap((inner) => inner.instructions)
+      .filter((ix) => accountKeys.get(ix.programIdIndex).equals(program.programId))
+      .map((ix) => Buffer.from(anchor.utils.bytes.bs58.decode(ix.data)))
+      .filter((data) => data.subarray(0, 8).equals(EVENT_IX_TAG))
+      .map((data) => program.coder.events.decode(data.subarray(8).toString("base64")))
+      .filter((event) => event !== null);
+  };
+
+  // Decodes the program events emitted by a confirmed transaction, whether
+  // logged or emitted through a self-CPI.
+  const eventsOf = async (signature: string) => {
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return [...parser.parseLogs(tx.meta.logMessages), ...cpiEventsOf(tx)];
+  };
+
+  const u64Le = (value: BN) => value.toArrayLike(Buffer, "le", 8);
+
+  // The `LockPreimage` of a `TokenLocked` event, as `request_refund` takes it.
+  const lockPreimage = (event: anchor.Event) => ({
+    nonce: event.data.nonce,
+    timestamp: event.data.timestamp,
+    amount: event.data.amount,
+    unicityRecipient: event.data.unicityRecipient,
+    memo: event.data.memo,
+    batchIndex: event.data.batchIndex,
+  });
+
+  const fund = async (to: PublicKey, lamports: number) => {
+    const tx = new Transaction().add(
+      SystemProgram.transfer({ fromPubkey: admin, toPubkey: to, lamports })
+    );
+    await provider.sendAndConfirm(tx);
+  };
+
+  // Minimal SPL Token helpers built from raw instructions, so the tests need no
+  // extra dependency. The provider wallet is the mint authority.
+  const TOKEN_PROGRAM_ID = anchor.utils.token.TOKEN_PROGRAM_ID;
+
+  const createTokenProgramAccount = async (
+    space: number,
+    init: (key: PublicKey) => Buffer,
+    keys: (key: PublicKey) => PublicKey[]
+  ) => {
+    const account = Keypair.generate();
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(space);
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: admin,
+        newAccountPubkey: account.publicKey,
+        lamports,
+        space,
+        programId: TOKEN_PROGRAM_ID,
+      }),
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: keys(account.publicKey).map((pubkey, i) => ({
+          pubkey,
+          isSigner: false,
+          isWritable: i === 0,
+        })),
+        data: init(account.publicKey),
+      })
+    );
+    await provider.sendAndConfirm(tx, [account]);
+    return account.publicKey;
+  };
+
+  // InitializeMint2 with no freeze authority.
+  const createMint = (decimals: number) =>
+    createTokenProgramAccount(
+      82,
+      () => Buffer.concat([Buffer.from([20, decimals]), admin.toBuffer(), Buffer.from([0])]),
+      (mint) => [mint]
+    );
+
+  // InitializeAccount3.
+  const createTokenAccount = (mint: PublicKey, owner: PublicKey) =>
+    createTokenProgramAccount(
+      165,
+      () => Buffer.concat([Buffer.from([18]), owner.toBuffer()]),
+      (account) => [account, mint]
+    );
+
+  const mintTo = async (mint: PublicKey, account: PublicKey, amount: number) => {
+    const tx = new Transaction().add(
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: account, isSigner: false, isWritable: true },
+          { pubkey: admin, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([7]), u64Le(new BN(amount))]),
+      })
+    );
+    await provider.sendAndConfirm(tx);
+  };
+
+  const tokenBalance = async (account: PublicKey) =>
+    Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
 
   it("Is initialized!", async () => {
     // Add your test here.
-    const provider = anchor.AnchorProvider.env();
-    const adminPubkey = provider.wallet.publicKey;
-    const tx = await program.methods.initialize(adminPubkey).rpc();
+    const tx = await program.methods.initialize(admin, new BN(0), CLUSTER_TAG).rpc();
     console.log("Your transaction signature", tx);
   });
+
+  it("Rejects a second initialize with AlreadyInitialized", async () => {
+    await expectFailure(program.methods.initialize(admin, new BN(0), CLUSTER_TAG).rpc(), /AlreadyInitialized/);
+    expect((await fetchState()).admin.equals(admin)).to.equal(true);
+  });
+
+  it("Rejects a replayed release of the same unicity_tx_id", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    await lockSol(amount);
+
+    const recipient = Keypair.generate().publicKey;
+    const unicityTxId = Array.from(Keypair.generate().publicKey.toBytes());
+    const release = () =>
+      program.methods
+        .releaseSol(amount, recipient, unicityTxId)
+        .accountsPartial({ recipient, admin })
+        .rpc();
+
+    const before = await fetchState();
+    await release();
+    expect(await provider.connection.getBalance(recipient)).to.equal(amount.toNumber());
+
+    const after = await fetchState();
+    expect(after.totalReleased.sub(before.totalReleased).eq(amount)).to.equal(true);
+    expect(after.totalLocked.eq(before.totalLocked)).to.equal(true);
+
+    await expectFailure(release(), /already in use/);
+  });
+
+  it("Enforces min and max lock amounts at the boundaries", async () => {
+    const min = new BN(LAMPORTS_PER_SOL / 100);
+    const max = new BN(LAMPORTS_PER_SOL / 50);
+    await program.methods.setLimits(min, max).accountsPartial({ admin }).rpc();
+
+    await expectFailure(lockSol(min.subn(1)), /BelowMinimum/);
+    await lockSol(min);
+    await lockSol(max);
+    await expectFailure(lockSol(max.addn(1)), /AboveMaximum/);
+
+    await expectFailure(
+      program.methods.setLimits(max, min).accountsPartial({ admin }).rpc(),
+      /InvalidLimits/
+    );
+
+    // A max of 0 means uncapped.
+    await program.methods.setLimits(new BN(0), new BN(0)).accountsPartial({ admin }).rpc();
+    await lockSol(max.addn(1));
+  });
+
+  it("Charges the bridge fee and only locks the net amount", async () => {
+    const feeCollector = Keypair.generate().publicKey;
+    await fund(feeCollector, LAMPORTS_PER_SOL / 100);
+    await program.methods.setFee(100, feeCollector).accountsPartial({ admin }).rpc();
+
+    const before = await fetchState();
+    const collectorBefore = await provider.connection.getBalance(feeCollector);
+
+    await lockSol(new BN(LAMPORTS_PER_SOL), feeCollector);
+
+    const after = await fetchState();
+    expect(await provider.connection.getBalance(feeCollector)).to.equal(
+      collectorBefore + LAMPORTS_PER_SOL / 100
+    );
+    expect(after.totalLocked.sub(before.totalLocked).toNumber()).to.equal(
+      (LAMPORTS_PER_SOL * 99) / 100
+    );
+
+    await expectFailure(
+      program.methods.setFee(1001, feeCollector).accountsPartial({ admin }).rpc(),
+      /FeeTooHigh/
+    );
+    await program.methods.setFee(0, admin).accountsPartial({ admin }).rpc();
+  });
+
+  it("Charges the discounted tier rate once a wallet crosses its threshold", async () => {
+    const user = Keypair.generate();
+    await fund(user.publicKey, LAMPORTS_PER_SOL);
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    const lockAsUser = async () => {
+      const signature = await program.methods
+        .lockSol(amount, unicityRecipient, new BN(0), new BN(0))
+        .accountsPartial({ user: user.publicKey, feeCollector: admin, recipientStats, blockedRecipient })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+      const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+      return event.data;
+    };
+
+    await program.methods.setFee(100, admin).accountsPartial({ admin }).rpc();
+    await program.methods
+      .setFeeTiers([{ threshold: new BN(LAMPORTS_PER_SOL / 20), feeBps: 50 }])
+      .accountsPartial({ admin })
+      .rpc();
+
+    const first = await lockAsUser();
+    expect(first.feeTier).to.equal(0);
+    expect(first.fee.toNumber()).to.equal(LAMPORTS_PER_SOL / 1000);
+
+    const second = await lockAsUser();
+    expect(second.feeTier).to.equal(1);
+    expect(second.fee.toNumber()).to.equal(LAMPORTS_PER_SOL / 2000);
+
+    await program.methods.setFeeTiers([]).accountsPartial({ admin }).rpc();
+    await program.methods.setFee(0, admin).accountsPartial({ admin }).rpc();
+  });
+
+  it("Accumulates per-user stats across locks", async () => {
+    const [userStatsPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("user"), admin.toBuffer()],
+      program.programId
+    );
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+
+    await lockSol(amount);
+    const before = await program.account.userStats.fetch(userStatsPda);
+    await lockSol(amount);
+    const after = await program.account.userStats.fetch(userStatsPda);
+    const state = await fetchState();
+
+    expect(after.lockCount.sub(before.lockCount).toNumber()).to.equal(1);
+    expect(after.totalLocked.sub(before.totalLocked).eq(amount)).to.equal(true);
+    expect(after.lastNonce.eq(state.nonce)).to.equal(true);
+  });
+
+  it("Keeps the escrow rent-exempt after an emergency withdraw", async () => {
+    await lockSol(new BN(LAMPORTS_PER_SOL / 10));
+    const schedule = () =>
+      program.methods.scheduleEmergencyWithdraw().accountsPartial({ admin }).rpc();
+    const execute = (reasonCode = ROUTINE) =>
+      program.methods.executeEmergencyWithdraw(reasonCode).accountsPartial({ admin }).rpc();
+
+    await expectFailure(execute(3), /InvalidReasonCode/);
+    await expectFailure(execute(), /EmergencyNotScheduled/);
+    // The suite initializes with a zero timelock, so a schedule is due at once.
+    await schedule();
+    const signature = await program.methods
+      .executeEmergencyWithdraw(INCIDENT)
+      .accountsPartial({ admin })
+      .rpc({ commitment: "confirmed" });
+    const [withdrawal] = (await eventsOf(signature)).filter(
+      (e) => e.name === "emergencyWithdrawal"
+    );
+    expect(withdrawal.data.version).to.equal(EVENT_VERSION);
+    expect(withdrawal.data.reasonCode).to.equal(INCIDENT);
+
+    const escrow = await provider.connection.getAccountInfo(escrowPda);
+    expect(escrow).to.not.equal(null);
+    const rentMinimum = await provider.connection.getMinimumBalanceForRentExemption(
+      escrow.data.length
+    );
+    expect(escrow.lamports).to.equal(rentMinimum);
+
+    await schedule();
+    await expectFailure(execute(), /NothingToWithdraw/);
+    await expectFailure(
+      program.methods.emergencyWithdrawAmount(new BN(1), ROUTINE).accountsPartial({ admin }).rpc(),
+      /NothingToWithdraw/
+    );
+    await program.methods.cancelEmergencyWithdraw().accountsPartial({ admin }).rpc();
+    await expectFailure(execute(), /EmergencyNotScheduled/);
+
+    // The withdraw leaves the accounting untouched, so locks stay refused until
+    // the escrow covers net_locked again.
+    await expectFailure(lockSol(new BN(LAMPORTS_PER_SOL / 100)), /SolvencyViolation/);
+    const state = await fetchState();
+    await fund(escrowPda, state.totalLocked.sub(state.totalReleased).toNumber());
+  });
+
+  it("Keeps the escrow solvent across a lock and release sequence", async () => {
+    const expectSolvent = async () => {
+      const state = await fetchState();
+      const escrow = await provider.connection.getBalance(escrowPda);
+      expect(escrow >= state.totalLocked.sub(state.totalReleased).toNumber()).to.equal(true);
+    };
+
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    await lockSol(amount);
+    await expectSolvent();
+
+    const recipient = Keypair.generate().publicKey;
+    await program.methods
+      .releaseSol(amount, recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ recipient, admin })
+      .rpc();
+    await expectSolvent();
+
+    await lockSol(amount);
+    await expectSolvent();
+  });
+
+  it("Locks a batch with one indexed event and nonce per entry", async () => {
+    const amounts = [LAMPORTS_PER_SOL / 100, LAMPORTS_PER_SOL / 50, LAMPORTS_PER_SOL / 20];
+    const before = await fetchState();
+    const escrowBefore = await provider.connection.getBalance(escrowPda);
+
+    const signature = await program.methods
+      .lockSolBatch(amounts.map((amount) => ({ amount: new BN(amount), unicityRecipient })))
+      .accountsPartial({ user: admin, feeCollector: admin })
+      .remainingAccounts(
+        amounts.map(() => ({ pubkey: blockedRecipient, isSigner: false, isWritable: false }))
+      )
+      .rpc({ commitment: "confirmed" });
+
+    const events = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    expect(events.map((e) => e.data.amount.toNumber())).to.deep.equal(amounts);
+    events.forEach((e, i) => expect(e.data.nonce.eq(before.nonce.addn(i + 1))).to.equal(true));
+    expect(events.map((e) => e.data.batchIndex)).to.deep.equal([0, 1, 2]);
+    expect(events.map((e) => e.data.batchSize)).to.deep.equal([3, 3, 3]);
+    const lockIds = new Set(events.map((e) => Buffer.from(e.data.lockId).toString("hex")));
+    expect(lockIds.size).to.equal(3);
+
+    const total = amounts.reduce((a, b) => a + b, 0);
+    expect(await provider.connection.getBalance(escrowPda)).to.equal(escrowBefore + total);
+
+    // The length is checked before the entries, so empty recipients keep the tx small.
+    const tooMany = Array.from({ length: 11 }, () => ({
+      amount: new BN(1),
+      unicityRecipient: "",
+    }));
+    await expectFailure(
+      program.methods
+        .lockSolBatch(tooMany)
+        .accountsPartial({ user: admin, feeCollector: admin })
+        .rpc(),
+      /BatchTooLarge/
+    );
+  });
+
+  it("Pays releases from the hot escrow only", async () => {
+    await lockSol(new BN(LAMPORTS_PER_SOL / 10));
+    const { hotLocked } = await fetchState();
+    const rebalance = (amount: BN, toCold: boolean) =>
+      program.methods.rebalanceEscrow(amount, toCold).accountsPartial({ admin }).rpc();
+    const release = (amount: BN) => {
+      const recipient = Keypair.generate().publicKey;
+      return program.methods
+        .releaseSol(amount, recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+        .accountsPartial({ recipient, admin })
+        .rpc();
+    };
+
+    await rebalance(hotLocked, true);
+    const [coldEscrowPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_cold")],
+      program.programId
+    );
+    expect(await provider.connection.getBalance(coldEscrowPda)).to.equal(hotLocked.toNumber());
+    await expectFailure(release(new BN(1)), /HotEscrowInsufficient/);
+
+    // Moving funds out of cold storage takes a due emergency schedule.
+    await expectFailure(rebalance(hotLocked, false), /EmergencyNotScheduled/);
+    await program.methods.scheduleEmergencyWithdraw().accountsPartial({ admin }).rpc();
+    await rebalance(hotLocked, false);
+
+    const state = await fetchState();
+    expect(state.hotLocked.eq(hotLocked)).to.equal(true);
+    expect(state.coldLocked.toNumber()).to.equal(0);
+    await release(new BN(1));
+  });
+
+  it("Sweeps stray escrow lamports to the fee collector", async () => {
+    const feeCollector = Keypair.generate().publicKey;
+    await fund(feeCollector, LAMPORTS_PER_SOL / 100);
+    await program.methods.setFee(0, feeCollector).accountsPartial({ admin }).rpc();
+
+    const stray = 12_345;
+    await fund(escrowPda, stray);
+    const escrow = await provider.connection.getAccountInfo(escrowPda);
+    const rentMinimum = await provider.connection.getMinimumBalanceForRentExemption(
+      escrow.data.length
+    );
+    const { hotLocked } = await fetchState();
+    const surplus = escrow.lamports - rentMinimum - hotLocked.toNumber();
+    expect(surplus >= stray).to.equal(true);
+
+    const collectorBefore = await provider.connection.getBalance(feeCollector);
+    await program.methods.reconcile().accountsPartial({ feeCollector, admin }).rpc();
+    expect(await provider.connection.getBalance(feeCollector)).to.equal(collectorBefore + surplus);
+    await expectFailure(
+      program.methods.reconcile().accountsPartial({ feeCollector, admin }).rpc(),
+      /NothingToWithdraw/
+    );
+
+    await program.methods.setFee(0, admin).accountsPartial({ admin }).rpc();
+  });
+
+  it("Processes queued releases in FIFO order", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    await lockSol(amount.muln(3));
+    const { releaseNonce } = await fetchState();
+
+    const recipients = [0, 1, 2].map(() => Keypair.generate().publicKey);
+    for (const recipient of recipients) {
+      await program.methods
+        .enqueueRelease(amount, recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+        .accountsPartial({ admin })
+        .rpc();
+    }
+
+    for (const [i, recipient] of recipients.entries()) {
+      const nonce = releaseNonce.addn(i);
+      const [queuedRelease] = PublicKey.findProgramAddressSync(
+        [Buffer.from("queued_release"), u64Le(nonce)],
+        program.programId
+      );
+      const queued = await program.account.queuedRelease.fetch(queuedRelease);
+      expect(queued.recipient.equals(recipient)).to.equal(true);
+
+      const signature = await program.methods
+        .processRelease()
+        .accountsPartial({ queuedRelease, recipient, payer: admin })
+        .rpc({ commitment: "confirmed" });
+      const [event] = (await eventsOf(signature)).filter((e) => e.name === "releaseProcessed");
+      expect(event.data.releaseNonce.eq(nonce)).to.equal(true);
+      expect(await provider.connection.getBalance(recipient)).to.equal(amount.toNumber());
+      expect(await provider.connection.getAccountInfo(queuedRelease)).to.equal(null);
+    }
+  });
+
+  it("Lets the admin cancel a stuck head of the release queue", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    await lockSol(amount);
+    const { releaseNonce } = await fetchState();
+    const queuedReleasePda = (nonce: BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("queued_release"), u64Le(nonce)],
+        program.programId
+      )[0];
+
+    // More than the bridge holds, so it can never be processed.
+    const stuckTxId = Array.from(Keypair.generate().publicKey.toBytes());
+    const stuckRecipient = Keypair.generate().publicKey;
+    await program.methods
+      .enqueueRelease(new BN(LAMPORTS_PER_SOL).muln(1_000), stuckRecipient, stuckTxId)
+      .accountsPartial({ admin })
+      .rpc();
+    const recipient = Keypair.generate().publicKey;
+    await program.methods
+      .enqueueRelease(amount, recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ admin })
+      .rpc();
+
+    const stuck = queuedReleasePda(releaseNonce);
+    await expectFailure(
+      program.methods
+        .processRelease()
+        .accountsPartial({ queuedRelease: stuck, recipient: stuckRecipient, payer: admin })
+        .rpc(),
+      /AccountingUnderflow|HotEscrowInsufficient/
+    );
+
+    const intruder = Keypair.generate();
+    await expectFailure(
+      program.methods
+        .cancelQueuedRelease()
+        .accountsPartial({ queuedRelease: stuck, payer: admin, admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc(),
+      /Unauthorized/
+    );
+    const signature = await program.methods
+      .cancelQueuedRelease()
+      .accountsPartial({ queuedRelease: stuck, payer: admin, admin })
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter(
+      (e) => e.name === "queuedReleaseCancelled"
+    );
+    expect(event.data.releaseNonce.eq(releaseNonce)).to.equal(true);
+    expect(await provider.connection.getAccountInfo(stuck)).to.equal(null);
+    const [processed] = PublicKey.findProgramAddressSync(
+      [Buffer.from("processed"), Buffer.from(stuckTxId)],
+      program.programId
+    );
+    expect(await provider.connection.getAccountInfo(processed)).to.equal(null);
+
+    // The entry behind it is next in line now.
+    const next = queuedReleasePda(releaseNonce.addn(1));
+    await program.methods
+      .processRelease()
+      .accountsPartial({ queuedRelease: next, recipient, payer: admin })
+      .rpc();
+    expect(await provider.connection.getBalance(recipient)).to.equal(amount.toNumber());
+  });
+
+  it("Lets only the admin adjust total_locked while paused", async () => {
+    const reason = Array.from(createHash("sha256").update("INC-1").digest());
+    const adjust = (newTotal: BN, signer?: Keypair) =>
+      program.methods
+        .adjustAccounting(newTotal, reason)
+        .accountsPartial({ admin: signer?.publicKey ?? admin })
+        .signers(signer ? [signer] : [])
+        .rpc({ commitment: "confirmed" });
+
+    const { totalLocked } = await fetchState();
+    await expectFailure(adjust(totalLocked.addn(1)), /BridgeNotPaused/);
+
+    await program.methods.setPaused(true).accountsPartial({ admin }).rpc();
+    await expectFailure(adjust(totalLocked.addn(1), Keypair.generate()), /Unauthorized/);
+
+    const [event] = (await eventsOf(await adjust(totalLocked.addn(1)))).filter(
+      (e) => e.name === "accountingAdjusted"
+    );
+    expect(event.data.oldTotalLocked.eq(totalLocked)).to.equal(true);
+    expect(event.data.newTotalLocked.eq(totalLocked.addn(1))).to.equal(true);
+    expect(Array.from(event.data.reason)).to.deep.equal(reason);
+
+    await adjust(totalLocked);
+    await program.methods.setPaused(false).accountsPartial({ admin }).rpc();
+  });
+
+  it("Hashes the same timestamp into lock_id that it emits", async () => {
+    const signature = await lockSol(new BN(LAMPORTS_PER_SOL / 100));
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+
+    const expected = createHash("sha256")
+      .update(
+        Buffer.concat([
+          admin.toBuffer(),
+          u64Le(event.data.nonce),
+          u64Le(event.data.timestamp),
+          u64Le(event.data.amount),
+          createHash("sha256").update(event.data.unicityRecipient, "utf8").digest(),
+          Buffer.from(event.data.memo),
+          Buffer.from([0, 0]),
+        ])
+      )
+      .digest();
+    expect(Buffer.from(event.data.lockId).equals(expected)).to.equal(true);
+    expect(event.data.version).to.equal(EVENT_VERSION);
+    expect(event.data.slot.toNumber()).to.be.greaterThan(0);
+
+    const state = await fetchState();
+    expect(state.lastLockTs.eq(event.data.timestamp)).to.equal(true);
+  });
+
+  it("Refuses a lock after its deadline", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const now = Math.floor(Date.now() / 1000);
+    await expectFailure(lockSol(amount, admin, new BN(now - 3600)), /DeadlineExceeded/);
+    await lockSol(amount, admin, new BN(now + 3600));
+  });
+
+  it("Round-trips a lock memo through the event", async () => {
+    const memo = Array.from(createHash("sha256").update("order-42").digest());
+    const signature = await program.methods
+      .lockSolWithMemo(new BN(LAMPORTS_PER_SOL / 100), unicityRecipient, new BN(0), memo)
+      .accountsPartial({ user: admin, feeCollector: admin, recipientStats, blockedRecipient })
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+
+    expect(Array.from(event.data.memo)).to.deep.equal(memo);
+  });
+
+  it("Returns the lock_id and daily headroom as return data", async () => {
+    const lock = async () => {
+      const signature = await lockSol(new BN(LAMPORTS_PER_SOL / 100));
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+      const data = Buffer.from(tx.meta.returnData.data[0], "base64");
+      expect(data.subarray(0, 32).equals(Buffer.from(event.data.lockId))).to.equal(true);
+      return new BN(data.subarray(32, 40), "le");
+    };
+
+    expect((await lock()).eq(new BN("18446744073709551615"))).to.equal(true);
+
+    await program.methods
+      .setDailyLimit(new BN(1_000 * LAMPORTS_PER_SOL))
+      .accountsPartial({ admin })
+      .rpc();
+    const headroom = await lock();
+    const { dailyLimit, dailyLocked } = await fetchState();
+    expect(headroom.eq(dailyLimit.sub(dailyLocked))).to.equal(true);
+    await program.methods.setDailyLimit(new BN(0)).accountsPartial({ admin }).rpc();
+  });
+
+  it("Chains consecutive locks into a verifiable hash chain", async () => {
+    const lockEvent = async () => {
+      const [event] = (await eventsOf(await lockSol(new BN(LAMPORTS_PER_SOL / 100)))).filter(
+        (e) => e.name === "tokenLocked"
+      );
+      return event.data;
+    };
+
+    const first = await lockEvent();
+    const second = await lockEvent();
+
+    expect(second.prevNonce.eq(first.nonce)).to.equal(true);
+    const expected = createHash("sha256")
+      .update(Buffer.concat([Buffer.from(first.sequenceHash), Buffer.from(second.lockId)]))
+      .digest();
+    expect(Buffer.from(second.sequenceHash).equals(expected)).to.equal(true);
+  });
+
+  it("Derives different lock_ids for locks differing only in amount", async () => {
+    const lockIdOf = async (amount: BN) => {
+      const [event] = (await eventsOf(await lockSol(amount))).filter(
+        (e) => e.name === "tokenLocked"
+      );
+      return Buffer.from(event.data.lockId);
+    };
+
+    const first = await lockIdOf(new BN(LAMPORTS_PER_SOL / 100));
+    const second = await lockIdOf(new BN(LAMPORTS_PER_SOL / 100 + 1));
+    expect(first.equals(second)).to.equal(false);
+  });
+
+  it("Rate-limits a wallet locking again inside the cooldown", async () => {
+    const user = Keypair.generate();
+    await fund(user.publicKey, LAMPORTS_PER_SOL);
+    const lockAsUser = () =>
+      program.methods
+        .lockSol(new BN(LAMPORTS_PER_SOL / 100), unicityRecipient, new BN(0), new BN(0))
+        .accountsPartial({ user: user.publicKey, feeCollector: admin, recipientStats, blockedRecipient })
+        .signers([user])
+        .rpc();
+
+    await program.methods.setMinInterval(new BN(3600)).accountsPartial({ admin }).rpc();
+    await lockAsUser();
+    await expectFailure(lockAsUser(), /RateLimited/);
+
+    await program.methods.setMinInterval(new BN(0)).accountsPartial({ admin }).rpc();
+    await lockAsUser();
+  });
+
+  it("Rejects a denied wallet until it is allowed again", async () => {
+    const user = Keypair.generate();
+    await fund(user.publicKey, LAMPORTS_PER_SOL);
+    const lockAsUser = () =>
+      program.methods
+        .lockSol(new BN(LAMPORTS_PER_SOL / 100), unicityRecipient, new BN(0), new BN(0))
+        .accountsPartial({ user: user.publicKey, feeCollector: admin, recipientStats, blockedRecipient })
+        .signers([user])
+        .rpc();
+
+    await program.methods.denyUser(user.publicKey).accountsPartial({ admin }).rpc();
+    await expectFailure(lockAsUser(), /UserDenied/);
+
+    await program.methods.allowUser(user.publicKey).accountsPartial({ admin }).rpc();
+    await lockAsUser();
+  });
+
+  it("Refunds an unprocessed lock exactly once", async () => {
+    const user = Keypair.generate();
+    await fund(user.publicKey, LAMPORTS_PER_SOL);
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+
+    const signature = await program.methods
+      .lockSol(amount, unicityRecipient, new BN(0), new BN(0))
+      .accountsPartial({ user: user.publicKey, feeCollector: admin, recipientStats, blockedRecipient })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    const lockId = event.data.lockId;
+
+    // Another wallet can neither claim the lock nor squat its refund request.
+    await expectFailure(
+      program.methods
+        .requestRefund(lockId, lockPreimage(event))
+        .accountsPartial({ user: admin })
+        .rpc(),
+      /LockIdMismatch/
+    );
+
+    await program.methods
+      .requestRefund(lockId, lockPreimage(event))
+      .accountsPartial({ user: user.publicKey })
+      .signers([user])
+      .rpc();
+
+    const before = await provider.connection.getBalance(user.publicKey);
+    await program.methods
+      .refundLock(lockId, user.publicKey, event.data.amount)
+      .accountsPartial({ user: user.publicKey, admin })
+      .rpc();
+    // The refund plus the closed request's rent go back to the user.
+    expect(await provider.connection.getBalance(user.publicKey)).to.be.greaterThan(
+      before + amount.toNumber()
+    );
+
+    await expectFailure(
+      program.methods.markLockProcessed(lockId).accountsPartial({ authority: admin }).rpc(),
+      /already in use/
+    );
+  });
+
+  it("Refuses to refund a lock already minted on Unicity", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const [event] = (await eventsOf(await lockSol(amount))).filter(
+      (e) => e.name === "tokenLocked"
+    );
+    const lockId = event.data.lockId;
+
+    await program.methods.markLockProcessed(lockId).accountsPartial({ authority: admin }).rpc();
+    await program.methods
+      .requestRefund(lockId, lockPreimage(event))
+      .accountsPartial({ user: admin })
+      .rpc();
+    await expectFailure(
+      program.methods
+        .refundLock(lockId, admin, amount)
+        .accountsPartial({ user: admin, admin })
+        .rpc(),
+      /already in use/
+    );
+  });
+
+  it("Keeps a separate escrow and accounting per mint", async () => {
+    const lockToken = async (amount: number) => {
+      const mint = await createMint(6);
+      const userTokenAccount = await createTokenAccount(mint, admin);
+      await mintTo(mint, userTokenAccount, 1_000);
+      await program.methods
+        .lockToken(new BN(amount), unicityRecipient)
+        .accountsPartial({ mint, userTokenAccount, blockedRecipient, user: admin })
+        .rpc();
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), mint.toBuffer()],
+        program.programId
+      );
+      const [assetStats] = PublicKey.findProgramAddressSync(
+        [Buffer.from("asset"), mint.toBuffer()],
+        program.programId
+      );
+      return { mint, escrow, assetStats };
+    };
+
+    const first = await lockToken(100);
+    const second = await lockToken(250);
+    expect(await tokenBalance(first.escrow)).to.equal(100);
+    expect(await tokenBalance(second.escrow)).to.equal(250);
+    const stats = await program.account.assetStats.fetch(second.assetStats);
+    expect(stats.totalLocked.toNumber()).to.equal(250);
+
+    const recipient = Keypair.generate().publicKey;
+    const recipientTokenAccount = await createTokenAccount(first.mint, recipient);
+    const release = (amount: number) =>
+      program.methods
+        .releaseToken(
+          new BN(amount),
+          recipient,
+          Array.from(Keypair.generate().publicKey.toBytes())
+        )
+        .accountsPartial({ mint: first.mint, recipientTokenAccount, admin })
+        .rpc();
+
+    await release(40);
+    expect(await tokenBalance(recipientTokenAccount)).to.equal(40);
+    expect(await tokenBalance(first.escrow)).to.equal(60);
+    expect(await tokenBalance(second.escrow)).to.equal(250);
+    await expectFailure(release(61), /AccountingUnderflow/);
+  });
+
+  it("Reports a healthy bridge through check_invariants", async () => {
+    await lockSol(new BN(LAMPORTS_PER_SOL / 100));
+    const signature = await program.methods.checkInvariants().rpc({ commitment: "confirmed" });
+
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "healthOk");
+    const state = await fetchState();
+    const escrow = await provider.connection.getBalance(escrowPda);
+    expect(event.data.netLocked.toString()).to.equal(
+      state.totalLocked.sub(state.totalReleased).toString()
+    );
+    expect(event.data.escrowBalance.toNumber()).to.equal(escrow);
+    expect(event.data.lifetimeVolume.eq(state.lifetimeVolume)).to.equal(true);
+  });
+
+  it("Counts wrapped SOL locks towards the shared total_locked", async () => {
+    const NATIVE_MINT = new PublicKey("So11111111111111111111111111111111111111112");
+    const userTokenAccount = await createTokenAccount(NATIVE_MINT, admin);
+    await fund(userTokenAccount, 5_000);
+    // SyncNative, so the token balance reflects the lamports just sent.
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        new TransactionInstruction({
+          programId: TOKEN_PROGRAM_ID,
+          keys: [{ pubkey: userTokenAccount, isSigner: false, isWritable: true }],
+          data: Buffer.from([17]),
+        })
+      )
+    );
+
+    const before = await fetchState();
+    const signature = await program.methods
+      .lockToken(new BN(5_000), unicityRecipient)
+      .accountsPartial({ mint: NATIVE_MINT, userTokenAccount, blockedRecipient, user: admin })
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "splTokenLocked");
+    expect(event.data.native).to.equal(true);
+    const afterLock = await fetchState();
+    expect(afterLock.totalLocked.sub(before.totalLocked).toNumber()).to.equal(5_000);
+    expect(afterLock.hotLocked.toString()).to.equal(before.hotLocked.toString());
+
+    // Released through the token path, it also counts towards total_released.
+    const recipient = Keypair.generate().publicKey;
+    const recipientTokenAccount = await createTokenAccount(NATIVE_MINT, recipient);
+    await program.methods
+      .releaseToken(new BN(5_000), recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ mint: NATIVE_MINT, recipientTokenAccount, admin })
+      .rpc();
+    const afterRelease = await fetchState();
+    expect(afterRelease.totalReleased.sub(before.totalReleased).toNumber()).to.equal(5_000);
+  });
+
+  it("Skips disabled optional events while locks still emit", async () => {
+    const EMIT_ALL = 0b1111;
+    // Clearing every bit still leaves the lock bit set.
+    await program.methods.setEmitFlags(0).accountsPartial({ admin }).rpc();
+    expect((await fetchState()).emitFlags).to.equal(1);
+    await expectFailure(
+      program.methods.setEmitFlags(1 << 4).accountsPartial({ admin }).rpc(),
+      /InvalidEmitFlags/
+    );
+
+    const lockEvents = await eventsOf(await lockSol(new BN(LAMPORTS_PER_SOL / 100)));
+    expect(lockEvents.some((e) => e.name === "tokenLocked")).to.equal(true);
+
+    const recipient = Keypair.generate().publicKey;
+    const release = () =>
+      program.methods
+        .releaseSol(new BN(1_000_000), recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+        .accountsPartial({ recipient, admin })
+        .rpc({ commitment: "confirmed" });
+    expect(await eventsOf(await release())).to.have.length(0);
+
+    await program.methods.setEmitFlags(EMIT_ALL).accountsPartial({ admin }).rpc();
+    const releaseEvents = await eventsOf(await release());
+    expect(releaseEvents.some((e) => e.name === "tokenReleased")).to.equal(true);
+  });
+
+  it("Refuses locks past the TVL cap until releases bring it down", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const state = await fetchState();
+    const netLocked = state.totalLocked.sub(state.totalReleased);
+    await program.methods.setTvlCap(netLocked.add(amount)).accountsPartial({ admin }).rpc();
+
+    await lockSol(amount);
+    await expectFailure(lockSol(new BN(1)), /TvlCapReached/);
+
+    const recipient = Keypair.generate().publicKey;
+    await program.methods
+      .releaseSol(amount, recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ recipient, admin })
+      .rpc();
+    await lockSol(amount);
+
+    await program.methods.setTvlCap(new BN(0)).accountsPartial({ admin }).rpc();
+  });
+
+  it("Requires the guardian to co-sign emergency withdraws once set", async () => {
+    const guardian = Keypair.generate();
+    await program.methods.setGuardian(guardian.publicKey).accountsPartial({ admin }).rpc();
+    await program.methods.scheduleEmergencyWithdraw().accountsPartial({ admin }).rpc();
+
+    const withdraw = program.methods.emergencyWithdrawAmount(new BN(1_000), ROUTINE);
+    await expectFailure(
+      withdraw.accountsPartial({ admin, guardian: null }).rpc(),
+      /GuardianRequired/
+    );
+    const signature = await withdraw
+      .accountsPartial({ admin, guardian: guardian.publicKey })
+      .signers([guardian])
+      .rpc({ commitment: "confirmed" });
+    const [withdrawal] = (await eventsOf(signature)).filter(
+      (e) => e.name === "emergencyWithdrawal"
+    );
+    expect(withdrawal.data.guardian.equals(guardian.publicKey)).to.equal(true);
+
+    // Put the withdrawn lamports back so the escrow still covers net_locked.
+    await fund(escrowPda, 1_000);
+    await program.methods.setGuardian(PublicKey.default).accountsPartial({ admin }).rpc();
+  });
+
+  it("Applies a registered partner's fee instead of the global one", async () => {
+    const partnerKeypair = Keypair.generate();
+    const partner = partnerKeypair.publicKey;
+    const [partnerConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("partner"), partner.toBuffer()],
+      program.programId
+    );
+    await expectFailure(
+      program.methods.registerPartner(partner, 1_001).accountsPartial({ admin }).rpc(),
+      /FeeTooHigh/
+    );
+    await program.methods.registerPartner(partner, 200).accountsPartial({ admin }).rpc();
+    await program.methods.setFee(100, admin).accountsPartial({ admin }).rpc();
+
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const lockWith = (partnerConfig: PublicKey | null, partner: Keypair | null) =>
+      program.methods
+        .lockSol(amount, unicityRecipient, new BN(0), new BN(0))
+        .accountsPartial({
+          user: admin,
+          feeCollector: admin,
+          recipientStats,
+          blockedRecipient,
+          partnerConfig,
+          partner: partner?.publicKey ?? null,
+        })
+        .signers(partner ? [partner] : [])
+        .rpc({ commitment: "confirmed" });
+    const lockEvent = async (partnerConfig: PublicKey | null, partner: Keypair | null) => {
+      const signature = await lockWith(partnerConfig, partner);
+      const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+      return event.data;
+    };
+
+    // Any front-end could pass the config; only the partner's signature unlocks its rate.
+    await expectFailure(lockWith(partnerConfig, null), /PartnerNotSigner/);
+    await expectFailure(lockWith(partnerConfig, Keypair.generate()), /PartnerNotSigner/);
+
+    const withPartner = await lockEvent(partnerConfig, partnerKeypair);
+    expect(withPartner.fee.toNumber()).to.equal(amount.toNumber() * 0.02);
+    expect(withPartner.partner.equals(partner)).to.equal(true);
+
+    const withoutPartner = await lockEvent(null, null);
+    expect(withoutPartner.fee.toNumber()).to.equal(amount.toNumber() * 0.01);
+    expect(withoutPartner.partner).to.equal(null);
+
+    await program.methods.setFee(0, admin).accountsPartial({ admin }).rpc();
+  });
+
+  it("Moves funds only once for locks sharing a client_key", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const clientKey = Array.from(Keypair.generate().publicKey.toBytes());
+    const lockReceipt = lockReceiptPda(admin, clientKey);
+    const lock = () =>
+      program.methods
+        .lockSolIdempotent(amount, unicityRecipient, new BN(0), clientKey)
+        .accountsPartial({
+          lock: { user: admin, feeCollector: admin, recipientStats, blockedRecipient },
+          lockReceipt,
+        })
+        .rpc({ commitment: "confirmed" });
+
+    // Another wallet copying the key opens its own receipt, not the admin's.
+    const copycat = Keypair.generate();
+    await fund(copycat.publicKey, LAMPORTS_PER_SOL / 10);
+    await program.methods
+      .lockSolIdempotent(amount, unicityRecipient, new BN(0), clientKey)
+      .accountsPartial({
+        lock: {
+          user: copycat.publicKey,
+          feeCollector: admin,
+          recipientStats,
+          blockedRecipient,
+        },
+        lockReceipt: lockReceiptPda(copycat.publicKey, clientKey),
+      })
+      .signers([copycat])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(lockReceipt)).to.equal(null);
+
+    const before = await fetchState();
+    const signature = await lock();
+    await expectFailure(lock(), /already in use/);
+    const after = await fetchState();
+    expect(after.totalLocked.sub(before.totalLocked).eq(amount)).to.equal(true);
+    expect(after.nonce.sub(before.nonce).toNumber()).to.equal(1);
+
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    const receipt = await program.account.lockReceipt.fetch(lockReceipt);
+    expect(receipt.lockId).to.deep.equal(event.data.lockId);
+    expect(receipt.user.equals(admin)).to.equal(true);
+  });
+
+  it("Locks a user's SOL while a sponsor pays fees and rent", async () => {
+    const user = Keypair.generate();
+    await fund(user.publicKey, LAMPORTS_PER_SOL / 10);
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+
+    const userBefore = await provider.connection.getBalance(user.publicKey);
+    const signature = await program.methods
+      .lockSolSponsored(amount, unicityRecipient, new BN(0))
+      .accountsPartial({
+        user: user.publicKey,
+        feePayer: admin,
+        feeCollector: admin,
+        recipientStats,
+        blockedRecipient,
+      })
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    // The provider wallet pays the transaction fee and the UserStats rent.
+    const userAfter = await provider.connection.getBalance(user.publicKey);
+    expect(userBefore - userAfter).to.equal(amount.toNumber());
+
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    expect(event.data.user.equals(user.publicKey)).to.equal(true);
+    expect(event.data.feePayer.equals(admin)).to.equal(true);
+  });
+
+  it("Resets the nonce while paused and resumes locking from it", async () => {
+    const { nonce } = await fetchState();
+    const newNonce = nonce.addn(1_000);
+    const reset = () =>
+      program.methods
+        .resetNonce(newNonce)
+        .accountsPartial({ admin })
+        .rpc({ commitment: "confirmed" });
+    await expectFailure(reset(), /BridgeNotPaused/);
+
+    await program.methods.setPaused(true).accountsPartial({ admin }).rpc();
+    const [event] = (await eventsOf(await reset())).filter(
+      (e) => e.name === "nonceReset"
+    );
+    await program.methods.setPaused(false).accountsPartial({ admin }).rpc();
+    expect(event.data.oldNonce.eq(nonce)).to.equal(true);
+    expect(event.data.newNonce.eq(newNonce)).to.equal(true);
+
+    const [lock] = (await eventsOf(await lockSol(new BN(LAMPORTS_PER_SOL / 100)))).filter(
+      (e) => e.name === "tokenLocked"
+    );
+    expect(lock.data.nonce.eq(newNonce.addn(1))).to.equal(true);
+    expect(lock.data.prevNonce.eq(newNonce)).to.equal(true);
+  });
+
+  it("Cancels a lock within the cancel window only", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const lockWithReceipt = async () => {
+      const clientKey = Array.from(Keypair.generate().publicKey.toBytes());
+      const lockReceipt = lockReceiptPda(admin, clientKey);
+      await program.methods
+        .lockSolIdempotent(amount, unicityRecipient, new BN(0), clientKey)
+        .accountsPartial({
+          lock: { user: admin, feeCollector: admin, recipientStats, blockedRecipient },
+          lockReceipt,
+        })
+        .rpc({ commitment: "confirmed" });
+      const { lockId } = await program.account.lockReceipt.fetch(lockReceipt);
+      return { lockId, lockReceipt };
+    };
+    const cancel = ({ lockId, lockReceipt }) =>
+      program.methods
+        .cancelLock(lockId)
+        .accountsPartial({ lockReceipt, user: admin })
+        .rpc({ commitment: "confirmed" });
+
+    await program.methods.setCancelWindow(new BN(3_600)).accountsPartial({ admin }).rpc();
+    const inWindow = await lockWithReceipt();
+    const before = await fetchState();
+    const [event] = (await eventsOf(await cancel(inWindow))).filter(
+      (e) => e.name === "lockCancelled"
+    );
+    expect(event.data.amount.eq(amount)).to.equal(true);
+    const after = await fetchState();
+    expect(before.totalLocked.sub(after.totalLocked).eq(amount)).to.equal(true);
+    expect(await provider.connection.getAccountInfo(inWindow.lockReceipt)).to.equal(null);
+
+    await program.methods.setCancelWindow(new BN(0)).accountsPartial({ admin }).rpc();
+    await expectFailure(cancel(await lockWithReceipt()), /CancelWindowExpired/);
+  });
+
+  it("Emits TokenLocked through a self-CPI instead of the program log", async () => {
+    const signature = await lockSol(new BN(LAMPORTS_PER_SOL / 100));
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const logged = Array.from(parser.parseLogs(tx.meta.logMessages));
+    expect(logged.some((e) => e.name === "tokenLocked")).to.equal(false);
+
+    const [event] = cpiEventsOf(tx).filter((e) => e.name === "tokenLocked");
+    expect(event.data.version).to.equal(EVENT_VERSION);
+    expect(event.data.user.equals(admin)).to.equal(true);
+    expect(event.data.amount.toNumber()).to.equal(LAMPORTS_PER_SOL / 100);
+  });
+
+  it("Aggregates the SOL bridged to a Unicity recipient", async () => {
+    const recipient = "ab".repeat(32);
+    const stats = recipientStatsPda(recipient);
+    const lock = (amount: number) =>
+      program.methods
+        .lockSol(new BN(amount), recipient, new BN(0), new BN(0))
+        .accountsPartial({
+          user: admin,
+          feeCollector: admin,
+          recipientStats: stats,
+          blockedRecipient: blockedRecipientPda(recipient),
+        })
+        .rpc();
+
+    await lock(LAMPORTS_PER_SOL / 100);
+    await lock(LAMPORTS_PER_SOL / 50);
+    const { totalBridged, count } = await program.account.recipientStats.fetch(stats);
+    expect(totalBridged.toNumber()).to.equal((3 * LAMPORTS_PER_SOL) / 100);
+    expect(count.toNumber()).to.equal(2);
+  });
+
+  it("Scales TokenLocked amounts into Unicity decimals", async () => {
+    await program.methods.setUnicityDecimals(12).accounts({ admin }).rpc();
+    const signature = await lockSol(new BN(LAMPORTS_PER_SOL / 100));
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    expect(event.data.unicityAmount.toString()).to.equal(
+      event.data.amount.mul(new BN(1_000)).toString()
+    );
+
+    await expectFailure(
+      program.methods.setUnicityDecimals(19).accounts({ admin }).rpc(),
+      /InvalidDecimals/
+    );
+    await program.methods.setUnicityDecimals(9).accounts({ admin }).rpc();
+  });
+
+  it("Pays the relayer its reward out of a signature-verified release", async () => {
+    const relayer = Keypair.generate();
+    await fund(relayer.publicKey, LAMPORTS_PER_SOL / 10);
+    await program.methods.setRelayer(relayer.publicKey).accounts({ admin }).rpc();
+    await program.methods.setRelayerReward(100).accounts({ admin }).rpc();
+    await expectFailure(
+      program.methods.setRelayerReward(101).accounts({ admin }).rpc(),
+      /RelayerRewardTooHigh/
+    );
+
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    await lockSol(amount);
+    const recipient = Keypair.generate().publicKey;
+    const unicityTxId = Keypair.generate().publicKey.toBuffer();
+    const message = Buffer.concat([u64Le(amount), recipient.toBuffer(), unicityTxId]);
+    const verify = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: relayer.secretKey,
+      message,
+    });
+    // web3.js lays the instruction out as offsets, public key, signature, message
+    const signature = Array.from(verify.data.subarray(48, 112));
+    const [processedRelease] = PublicKey.findProgramAddressSync(
+      [Buffer.from("processed"), unicityTxId],
+      program.programId
+    );
+
+    const relayerBefore = await provider.connection.getBalance(relayer.publicKey);
+    const claim = await program.methods
+      .claimWithSignature(amount, recipient, Array.from(unicityTxId), signature)
+      .accountsPartial({ recipient, payer: relayer.publicKey })
+      .preInstructions([verify])
+      .signers([relayer])
+      .rpc();
+
+    const reward = amount.toNumber() / 100;
+    const rent = await provider.connection.getBalance(processedRelease);
+    expect(await provider.connection.getBalance(relayer.publicKey)).to.equal(
+      relayerBefore + reward - rent
+    );
+    expect(await provider.connection.getBalance(recipient)).to.equal(amount.toNumber() - reward);
+    const [event] = (await eventsOf(claim)).filter((e) => e.name === "tokenReleased");
+    expect(event.data.relayer.equals(relayer.publicKey)).to.equal(true);
+    expect(event.data.relayerReward.toNumber()).to.equal(reward);
+
+    await program.methods.setRelayerReward(0).accounts({ admin }).rpc();
+  });
+
+  it("Pauses locks and releases independently", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    await lockSol(amount.muln(4));
+    const release = () =>
+      program.methods
+        .releaseSol(amount, admin, Array.from(Keypair.generate().publicKey.toBytes()))
+        .accountsPartial({ recipient: admin, admin })
+        .rpc();
+
+    for (const [locksPaused, releasesPaused] of [
+      [false, false],
+      [true, false],
+      [false, true],
+      [true, true],
+    ]) {
+      await program.methods.setPauseFlags(locksPaused, releasesPaused).accounts({ admin }).rpc();
+      if (locksPaused) {
+        await expectFailure(lockSol(amount), /LocksPaused/);
+      } else {
+        await lockSol(amount);
+      }
+      if (releasesPaused) {
+        await expectFailure(release(), /ReleasesPaused/);
+      } else {
+        await release();
+      }
+    }
+
+    await program.methods.setPauseFlags(false, false).accounts({ admin }).rpc();
+  });
+
+  it("Blocks and unblocks a Unicity recipient", async () => {
+    const recipient = "cd".repeat(32);
+    const blocked = blockedRecipientPda(recipient);
+    const lock = () =>
+      program.methods
+        .lockSol(new BN(LAMPORTS_PER_SOL / 100), recipient.toUpperCase(), new BN(0), new BN(0))
+        .accountsPartial({
+          user: admin,
+          feeCollector: admin,
+          recipientStats: recipientStatsPda(recipient),
+          blockedRecipient: blocked,
+        })
+        .rpc();
+
+    await program.methods.blockRecipient(recipient).accountsPartial({ admin }).rpc();
+    const { recipientKey } = await program.account.blockedRecipient.fetch(blocked);
+    expect(Buffer.from(recipientKey).equals(
+      createHash("sha256").update(recipient).digest()
+    )).to.equal(true);
+    await expectFailure(lock(), /RecipientBlocked/);
+
+    await program.methods.unblockRecipient(recipient).accountsPartial({ admin }).rpc();
+    expect(await provider.connection.getAccountInfo(blocked)).to.equal(null);
+    await lock();
+  });
+
+  it("Folds lock_ids into the epoch root and finalizes it on roll", async () => {
+    const epochPda = (epoch: BN) =>
+      PublicKey.findProgramAddressSync([Buffer.from("epoch"), u64Le(epoch)], program.programId)[0];
+    const roll = async () => {
+      const { currentEpoch } = await fetchState();
+      await program.methods
+        .rollEpoch()
+        .accountsPartial({ epochCommitment: epochPda(currentEpoch), authority: admin })
+        .rpc();
+      return program.account.epochCommitment.fetch(epochPda(currentEpoch));
+    };
+
+    // Start from an epoch holding only the locks below
+    await roll();
+    let root = Buffer.alloc(32);
+    for (const amount of [LAMPORTS_PER_SOL / 100, LAMPORTS_PER_SOL / 50]) {
+      const signature = await lockSol(new BN(amount));
+      const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+      const lockId = Buffer.from(event.data.lockId);
+      root = createHash("sha256").update(Buffer.concat([root, lockId])).digest();
+    }
+    expect(Buffer.from((await fetchState()).epochRoot).equals(root)).to.equal(true);
+
+    const commitment = await roll();
+    expect(Buffer.from(commitment.root).equals(root)).to.equal(true);
+    expect(commitment.lockCount.toNumber()).to.equal(2);
+    const state = await fetchState();
+    expect(state.currentEpoch.eq(commitment.epoch.addn(1))).to.equal(true);
+    expect(Buffer.from(state.epochRoot).equals(Buffer.alloc(32))).to.equal(true);
+  });
+
+  it("Leaves an up-to-date bridge state untouched on migrate_state", async () => {
+    const before = await provider.connection.getAccountInfo(bridgeStatePda);
+    const signature = await program.methods
+      .migrateState()
+      .accountsPartial({ admin })
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "stateMigrated");
+    expect(event.data.oldSpace.toNumber()).to.equal(before.data.length);
+    expect(event.data.space.toNumber()).to.equal(before.data.length);
+    expect((await fetchState()).admin.equals(admin)).to.equal(true);
+
+    const stranger = Keypair.generate();
+    await fund(stranger.publicKey, LAMPORTS_PER_SOL / 100);
+    await expectFailure(
+      program.methods
+        .migrateState()
+        .accountsPartial({ admin: stranger.publicKey })
+        .signers([stranger])
+        .rpc(),
+      /Unauthorized/
+    );
+  });
+
+  // A real vault lock needs a vault program to sign through a CPI; this test
+  // only covers the registration and the derivation check.
+  it("Only locks from PDAs of the registered vault program", async () => {
+    const vaultProgram = Keypair.generate().publicKey;
+    const lockFrom = (vaultSeeds: Buffer[]) =>
+      program.methods
+        .lockSolFromPda(new BN(LAMPORTS_PER_SOL / 100), unicityRecipient, vaultSeeds)
+        .accountsPartial({ user: admin, feeCollector: admin, recipientStats, blockedRecipient })
+        .rpc();
+    const [, bump] = PublicKey.findProgramAddressSync([Buffer.from("vault")], vaultProgram);
+    const vaultSeeds = [Buffer.from("vault"), Buffer.from([bump])];
+
+    await expectFailure(lockFrom(vaultSeeds), /VaultProgramNotSet/);
+    await program.methods.setVaultProgram(vaultProgram).accounts({ admin }).rpc();
+    expect((await fetchState()).vaultProgram.equals(vaultProgram)).to.equal(true);
+    // The provider wallet is not the vault PDA
+    await expectFailure(lockFrom(vaultSeeds), /InvalidVault/);
+
+    await program.methods.setVaultProgram(PublicKey.default).accounts({ admin }).rpc();
+  });
+
+  it("Stamps the cluster tag into lock and release events", async () => {
+    expect((await fetchState()).clusterTag).to.equal(CLUSTER_TAG);
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+
+    const lock = await lockSol(amount);
+    const [locked] = (await eventsOf(lock)).filter((e) => e.name === "tokenLocked");
+    expect(locked.data.clusterTag).to.equal(CLUSTER_TAG);
+
+    const release = await program.methods
+      .releaseSol(amount, admin, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ recipient: admin, admin })
+      .rpc({ commitment: "confirmed" });
+    const [released] = (await eventsOf(release)).filter((e) => e.name === "tokenReleased");
+    expect(released.data.clusterTag).to.equal(CLUSTER_TAG);
+  });
+
+  it("Records a pending lock receipt and closes it once the lock is processed", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const receiptKey = Array.from(Keypair.generate().publicKey.toBytes());
+    const [pendingLock] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pending"), admin.toBuffer(), Buffer.from(receiptKey)],
+      program.programId
+    );
+    const signature = await program.methods
+      .lockSolWithReceipt(amount, unicityRecipient, new BN(0), receiptKey)
+      .accountsPartial({
+        lock: { user: admin, feeCollector: admin, recipientStats, blockedRecipient },
+        pendingLock,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    const receipt = await program.account.pendingLock.fetch(pendingLock);
+    expect(receipt.user.equals(admin)).to.equal(true);
+    expect(receipt.lockId).to.deep.equal(event.data.lockId);
+    expect(receipt.amount.eq(amount)).to.equal(true);
+    expect(receipt.unicityRecipient).to.equal(unicityRecipient);
+    expect(receipt.nonce.eq(event.data.nonce)).to.equal(true);
+
+    const close = () =>
+      program.methods.closePendingLock().accountsPartial({ pendingLock, user: admin }).rpc();
+    await expectFailure(close(), /AccountNotInitialized/);
+
+    await program.methods
+      .markLockProcessed(event.data.lockId)
+      .accountsPartial({ authority: admin })
+      .rpc();
+    await close();
+    expect(await provider.connection.getAccountInfo(pendingLock)).to.equal(null);
+  });
+
+  it("Requires checksummed recipients once the admin enables it", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    // `unicityRecipient` with its `verify_recipient_checksum` casing.
+    const checksummed = "15ED2F7f97c6E98C15d8Dc4bA8beF3eBEFC5Ebf049Dab7cdD075d334a6bBA2F9";
+    await program.methods.setChecksumRequired(true).accountsPartial({ admin }).rpc();
+
+    await expectFailure(lockSol(amount), /InvalidRecipientChecksum/);
+    await program.methods
+      .lockSol(amount, checksummed, new BN(0), new BN(0))
+      .accountsPartial({ user: admin, feeCollector: admin, recipientStats, blockedRecipient })
+      .rpc();
+
+    await program.methods.setChecksumRequired(false).accountsPartial({ admin }).rpc();
+    await lockSol(amount);
+  });
+
+  it("Sweeps fees accumulated in the fee vault to the treasury", async () => {
+    const [feeVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("fee_vault")],
+      program.programId
+    );
+    const treasury = Keypair.generate().publicKey;
+    const collect = (amount: BN) =>
+      program.methods.collectFees(amount).accountsPartial({ treasury, admin }).rpc();
+
+    await expectFailure(collect(new BN(0)), /TreasuryNotSet/);
+    await program.methods.setTreasury(treasury).accountsPartial({ admin }).rpc();
+    await fund(treasury, await provider.connection.getMinimumBalanceForRentExemption(0));
+    await fund(feeVault, await provider.connection.getMinimumBalanceForRentExemption(0));
+    await expectFailure(collect(new BN(0)), /NothingToWithdraw/);
+
+    await program.methods.setFee(100, feeVault).accountsPartial({ admin }).rpc();
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    await lockSol(amount, feeVault);
+    await lockSol(amount, feeVault);
+    const fees = (2 * amount.toNumber()) / 100;
+
+    await expectFailure(collect(new BN(fees + 1)), /InsufficientFees/);
+    const before = await fetchState();
+    const treasuryBefore = await provider.connection.getBalance(treasury);
+    await collect(new BN(fees / 2));
+    await collect(new BN(0));
+    expect((await provider.connection.getBalance(treasury)) - treasuryBefore).to.equal(fees);
+    const after = await fetchState();
+    expect(after.totalFeesCollected.sub(before.totalFeesCollected).toNumber()).to.equal(fees);
+
+    await program.methods.setFee(0, admin).accountsPartial({ admin }).rpc();
+  });
+
+  it("Releases a lock made under a single relayer through a later 2-of-3 set", async () => {
+    const relayer = Keypair.generate();
+    await program.methods.setRelayer(relayer.publicKey).accounts({ admin }).rpc();
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    await lockSol(amount);
+
+    const validators = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    await program.methods
+      .setValidators(validators.map((v) => v.publicKey), 2)
+      .accounts({ admin })
+      .rpc();
+
+    const recipient = Keypair.generate().publicKey;
+    const unicityTxId = Keypair.generate().publicKey.toBuffer();
+    const message = Buffer.concat([u64Le(amount), recipient.toBuffer(), unicityTxId]);
+    const verifies = validators.slice(0, 2).map((validator) =>
+      Ed25519Program.createInstructionWithPrivateKey({ privateKey: validator.secretKey, message })
+    );
+    // See the relayer reward test for the signature offset
+    const signatures = verifies.map((verify) => Array.from(verify.data.subarray(48, 112)));
+    await program.methods
+      .releaseSolMultisig(amount, recipient, Array.from(unicityTxId), signatures)
+      .accountsPartial({ recipient, payer: admin })
+      .preInstructions(verifies)
+      .rpc();
+    expect(await provider.connection.getBalance(recipient)).to.equal(amount.toNumber());
+
+    await program.methods.setValidators([], 0).accounts({ admin }).rpc();
+  });
+
+  it("Sweeps an escrow surplus below the dust threshold", async () => {
+    const sweep = () =>
+      program.methods
+        .sweepDust()
+        .accountsPartial({ feeCollector: admin, admin })
+        .rpc({ commitment: "confirmed" });
+    // Start from an escrow without surplus; earlier tests may have left some.
+    const reconcile = () =>
+      program.methods.reconcile().accountsPartial({ feeCollector: admin, admin }).rpc();
+    await reconcile().catch(() => {});
+    await program.methods.setDustThreshold(new BN(10)).accountsPartial({ admin }).rpc();
+    await expectFailure(sweep(), /NothingToWithdraw/);
+
+    await fund(escrowPda, 7);
+    const [event] = (await eventsOf(await sweep())).filter((e) => e.name === "dustSwept");
+    expect(event.data.amount.toNumber()).to.equal(7);
+
+    await fund(escrowPda, 10);
+    await expectFailure(sweep(), /SurplusAboveDustThreshold/);
+    await reconcile();
+    await program.methods.setDustThreshold(new BN(0)).accountsPartial({ admin }).rpc();
+  });
+
+  it("Splits the lock fee between the referrer and the fee collector", async () => {
+    const feeCollector = Keypair.generate().publicKey;
+    const referrer = Keypair.generate().publicKey;
+    const rentMinimum = await provider.connection.getMinimumBalanceForRentExemption(0);
+    await fund(feeCollector, rentMinimum);
+    await fund(referrer, rentMinimum);
+    await program.methods.setFee(100, feeCollector).accountsPartial({ admin }).rpc();
+    await expectFailure(
+      program.methods.setReferralBps(101).accountsPartial({ admin }).rpc(),
+      /ReferralTooHigh/
+    );
+    await program.methods.setReferralBps(40).accountsPartial({ admin }).rpc();
+
+    const referrerConfigPda = (referrer: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("referrer"), referrer.toBuffer()],
+        program.programId
+      )[0];
+
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    const lock = (referrer: PublicKey, referrerConfig = referrerConfigPda(referrer)) =>
+      program.methods
+        .lockSol(amount, unicityRecipient, new BN(0), new BN(0))
+        .accountsPartial({
+          user: admin,
+          feeCollector,
+          recipientStats,
+          blockedRecipient,
+          referrer,
+          referrerConfig,
+        })
+        .rpc({ commitment: "confirmed" });
+    await expectFailure(lock(admin, null), /InvalidReferrer/);
+    // Only referrers the admin registered earn a cut.
+    await expectFailure(lock(referrer, null), /ReferrerNotRegistered/);
+    await program.methods.registerReferrer(referrer).accountsPartial({ admin }).rpc();
+    const other = Keypair.generate().publicKey;
+    await program.methods.registerReferrer(other).accountsPartial({ admin }).rpc();
+    await expectFailure(lock(referrer, referrerConfigPda(other)), /ReferrerNotRegistered/);
+
+    const [event] = (await eventsOf(await lock(referrer))).filter(
+      (e) => e.name === "tokenLocked"
+    );
+    const fee = amount.toNumber() / 100;
+    const referralFee = (amount.toNumber() * 40) / 10_000;
+    expect(event.data.referrer.equals(referrer)).to.equal(true);
+    expect(event.data.referralFee.toNumber()).to.equal(referralFee);
+    expect(await provider.connection.getBalance(referrer)).to.equal(rentMinimum + referralFee);
+    expect(await provider.connection.getBalance(feeCollector)).to.equal(
+      rentMinimum + fee - referralFee
+    );
+
+    await program.methods.removeReferrer(referrer).accountsPartial({ admin }).rpc();
+    await program.methods.removeReferrer(other).accountsPartial({ admin }).rpc();
+    await expectFailure(lock(referrer), /AccountNotInitialized/);
+
+    await program.methods.setReferralBps(0).accountsPartial({ admin }).rpc();
+    await program.methods.setFee(0, admin).accountsPartial({ admin }).rpc();
+  });
+
+  it("Refuses a nonce-bound emergency withdraw scheduled before a lock", async () => {
+    const schedule = () =>
+      program.methods.scheduleEmergencyWithdraw().accountsPartial({ admin }).rpc();
+    const withdraw = () =>
+      program.methods.emergencyWithdrawAmount(new BN(1), ROUTINE).accountsPartial({ admin }).rpc();
+    await program.methods.setEmergencyNonceBinding(true).accountsPartial({ admin }).rpc();
+
+    await schedule();
+    await lockSol(new BN(LAMPORTS_PER_SOL / 100));
+    await expectFailure(withdraw(), /EmergencyScheduleStale/);
+
+    // Re-scheduling after the lock makes the withdraw executable again.
+    await program.methods.cancelEmergencyWithdraw().accountsPartial({ admin }).rpc();
+    await schedule();
+    await withdraw();
+    await fund(escrowPda, 1);
+
+    await program.methods.setEmergencyNonceBinding(false).accountsPartial({ admin }).rpc();
+  });
+
+  it("Books prepaid destination gas apart from the bridged amount", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const destGas = new BN(5_000);
+    const before = await fetchState();
+    const escrowBefore = await provider.connection.getBalance(escrowPda);
+
+    const signature = await program.methods
+      .lockSol(amount, unicityRecipient, new BN(0), destGas)
+      .accountsPartial({ user: admin, feeCollector: admin, recipientStats, blockedRecipient })
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    expect(event.data.amount.eq(amount)).to.equal(true);
+    expect(event.data.destGas.eq(destGas)).to.equal(true);
+
+    const after = await fetchState();
+    expect(after.totalLocked.sub(before.totalLocked).eq(amount)).to.equal(true);
+    expect(after.totalDestGas.sub(before.totalDestGas).eq(destGas)).to.equal(true);
+    expect((await provider.connection.getBalance(escrowPda)) - escrowBefore).to.equal(
+      amount.add(destGas).toNumber()
+    );
+  });
+
+  it("Accepts both relayers during a rotation and only the new one after", async () => {
+    const oldRelayer = Keypair.generate();
+    const newRelayer = Keypair.generate();
+    await program.methods.setRelayer(oldRelayer.publicKey).accounts({ admin }).rpc();
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    await lockSol(amount.muln(3));
+
+    const claim = (relayer: Keypair) => {
+      const recipient = Keypair.generate().publicKey;
+      const unicityTxId = Keypair.generate().publicKey.toBuffer();
+      const message = Buffer.concat([u64Le(amount), recipient.toBuffer(), unicityTxId]);
+      const verify = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: relayer.secretKey,
+        message,
+      });
+      // See the relayer reward test for the signature offset
+      const signature = Array.from(verify.data.subarray(48, 112));
+      return program.methods
+        .claimWithSignature(amount, recipient, Array.from(unicityTxId), signature)
+        .accountsPartial({ recipient, payer: admin })
+        .preInstructions([verify])
+        .rpc();
+    };
+
+    await expectFailure(
+      program.methods.rotateRelayer(newRelayer.publicKey, new BN(-1)).accounts({ admin }).rpc(),
+      /InvalidGracePeriod/
+    );
+    const rotate = (graceSecs: number) =>
+      program.methods
+        .rotateRelayer(newRelayer.publicKey, new BN(graceSecs))
+        .accounts({ admin })
+        .rpc({ commitment: "confirmed" });
+    const [scheduled] = (await eventsOf(await rotate(3600))).filter(
+      (e) => e.name === "relayerRotationScheduled"
+    );
+    expect(scheduled.data.relayer.equals(oldRelayer.publicKey)).to.equal(true);
+    expect(scheduled.data.nextRelayer.equals(newRelayer.publicKey)).to.equal(true);
+    await claim(oldRelayer);
+    await claim(newRelayer);
+
+    // Rescheduling with no grace ends the window at once
+    await rotate(0);
+    await expectFailure(claim(oldRelayer), /InvalidSignature/);
+    await claim(newRelayer);
+  });
+
+  it("Measures recipients in bytes and keeps a full-length one intact", async () => {
+    const lock = (recipient: string) =>
+      program.methods
+        .lockSol(new BN(LAMPORTS_PER_SOL / 100), recipient, new BN(0), new BN(0))
+        .accountsPartial({
+          user: admin,
+          feeCollector: admin,
+          recipientStats: recipientStatsPda(recipient),
+          blockedRecipient: blockedRecipientPda(recipient),
+        })
+        .rpc({ commitment: "confirmed" });
+
+    // 64 bytes, but only 32 characters and none of them hex
+    const multiByte = "é".repeat(32);
+    expect(Buffer.byteLength(multiByte)).to.equal(64);
+    await expectFailure(lock(multiByte), /InvalidRecipient/);
+
+    const fullLength = "ef".repeat(32);
+    const [event] = (await eventsOf(await lock(fullLength))).filter(
+      (e) => e.name === "tokenLocked"
+    );
+    expect(event.data.unicityRecipient).to.equal(fullLength);
+  });
+
+  it("Pauses itself when locks outpace the velocity limit", async () => {
+    await expectFailure(
+      program.methods.setLockVelocityLimit(2, new BN(-1)).accountsPartial({ admin }).rpc(),
+      /InvalidVelocityWindow/
+    );
+    await program.methods.setLockVelocityLimit(2, new BN(3600)).accountsPartial({ admin }).rpc();
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    await lockSol(amount);
+    await lockSol(amount);
+    expect((await fetchState()).paused).to.equal(false);
+
+    const signature = await program.methods
+      .lockSol(amount, unicityRecipient, new BN(0), new BN(0))
+      .accountsPartial({ user: admin, feeCollector: admin, recipientStats, blockedRecipient })
+      .rpc({ commitment: "confirmed" });
+    const [tripped] = (await eventsOf(signature)).filter((e) => e.name === "autoPaused");
+    expect(tripped.data.windowLocks).to.equal(3);
+    expect((await fetchState()).paused).to.equal(true);
+    await expectFailure(lockSol(amount), /BridgePaused/);
+
+    await program.methods.setPaused(false).accountsPartial({ admin }).rpc();
+    await lockSol(amount);
+    await program.methods.setLockVelocityLimit(0, new BN(0)).accountsPartial({ admin }).rpc();
+  });
+
+  it("Keeps lifetime volume growing while releases lower the net liability", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const before = await fetchState();
+
+    await lockSol(amount);
+    const recipient = Keypair.generate().publicKey;
+    await program.methods
+      .releaseSol(amount.muln(2), recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ recipient, admin })
+      .rpc();
+
+    const after = await fetchState();
+    const netLocked = (state: typeof before) => state.totalLocked.sub(state.totalReleased);
+    expect(after.lifetimeVolume.sub(before.lifetimeVolume).eq(amount)).to.equal(true);
+    expect(netLocked(before).sub(netLocked(after)).eq(amount)).to.equal(true);
+  });
+
+  it("Logs the lock_id in base58", async () => {
+    const signature = await program.methods
+      .lockSol(new BN(LAMPORTS_PER_SOL / 100), unicityRecipient, new BN(0), new BN(0))
+      .accountsPartial({ user: admin, feeCollector: admin, recipientStats, blockedRecipient })
+      .rpc({ commitment: "confirmed" });
+    const [event] = (await eventsOf(signature)).filter((e) => e.name === "tokenLocked");
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const lockId = anchor.utils.bytes.bs58.encode(Buffer.from(event.data.lockId));
+    expect(tx.meta.logMessages).to.include(`Program log: lock_id ${lockId}`);
+  });
+
+  it("Holds users to their bound recipient while bindings are required", async () => {
+    const [binding] = PublicKey.findProgramAddressSync(
+      [Buffer.from("binding"), admin.toBuffer()],
+      program.programId
+    );
+    const other = "ab".repeat(32);
+    const lock = (recipient: string, recipientBinding: PublicKey | null) =>
+      program.methods
+        .lockSol(new BN(LAMPORTS_PER_SOL / 100), recipient, new BN(0), new BN(0))
+        .accountsPartial({
+          user: admin,
+          feeCollector: admin,
+          recipientStats: recipientStatsPda(recipient),
+          blockedRecipient: blockedRecipientPda(recipient),
+          recipientBinding,
+        })
+        .rpc();
+    const setRequired = (required: boolean) =>
+      program.methods.setBindingsRequired(required).accountsPartial({ admin }).rpc();
+
+    await program.methods.bindRecipient(admin, unicityRecipient).accountsPartial({ admin }).rpc();
+    // Disabled: any recipient goes, with or without the binding
+    await lock(other, null);
+    await lock(other, binding);
+
+    await setRequired(true);
+    await lock(unicityRecipient, binding);
+    await expectFailure(lock(other, binding), /RecipientNotBound/);
+    await expectFailure(lock(unicityRecipient, null), /RecipientNotBound/);
+
+    await setRequired(false);
+  });
+
+  it("Splits an emergency withdraw 70/30 across treasury wallets", async () => {
+    const [first, second] = [Keypair.generate().publicKey, Keypair.generate().publicKey];
+    await expectFailure(
+      program.methods
+        .setWithdrawSplits([{ recipient: first, bps: 7_000 }])
+        .accountsPartial({ admin })
+        .rpc(),
+      /InvalidWithdrawSplits/
+    );
+    await program.methods
+      .setWithdrawSplits([
+        { recipient: first, bps: 7_000 },
+        { recipient: second, bps: 3_000 },
+      ])
+      .accountsPartial({ admin })
+      .rpc();
+    // Both wallets start rent-exempt, so the test only measures the split
+    await fund(first, LAMPORTS_PER_SOL / 100);
+    await fund(second, LAMPORTS_PER_SOL / 100);
+    const firstBefore = await provider.connection.getBalance(first);
+    const secondBefore = await provider.connection.getBalance(second);
+
+    const amount = new BN(1_000_000);
+    await lockSol(amount);
+    await program.methods.scheduleEmergencyWithdraw().accountsPartial({ admin }).rpc();
+    const withdraw = (recipients: PublicKey[]) =>
+      program.methods
+        .emergencyWithdrawAmount(amount, ROUTINE)
+        .accountsPartial({ admin })
+        .remainingAccounts(
+          recipients.map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+        )
+        .rpc({ commitment: "confirmed" });
+    await expectFailure(withdraw([second, first]), /InvalidWithdrawSplitAccount/);
+
+    const signature = await withdraw([first, second]);
+    expect((await provider.connection.getBalance(first)) - firstBefore).to.equal(700_000);
+    expect((await provider.connection.getBalance(second)) - secondBefore).to.equal(300_000);
+    const splits = (await eventsOf(signature)).filter(
+      (e) => e.name === "emergencyWithdrawalSplit"
+    );
+    expect(splits.map((e) => e.data.amount.toNumber())).to.deep.equal([700_000, 300_000]);
+
+    await fund(escrowPda, amount.toNumber());
+    await program.methods.setWithdrawSplits([]).accountsPartial({ admin }).rpc();
+  });
+
+  // Kills the bridge, so only the closing test may follow.
+  it("Refuses locks but still refunds once the bridge is killed", async () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 100);
+    const [event] = (await eventsOf(await lockSol(amount))).filter(
+      (e) => e.name === "tokenLocked"
+    );
+    const lockId = event.data.lockId;
+
+    await program.methods.killBridge().accountsPartial({ admin }).rpc();
+    expect((await fetchState()).killed).to.equal(true);
+    await expectFailure(lockSol(amount), /BridgeKilled/);
+    await expectFailure(
+      program.methods.killBridge().accountsPartial({ admin }).rpc(),
+      /BridgeKilled/
+    );
+
+    // Unpausing does not revive a killed bridge.
+    await program.methods.setPaused(false).accountsPartial({ admin }).rpc();
+    await expectFailure(lockSol(amount), /BridgeKilled/);
+
+    await program.methods
+      .requestRefund(lockId, lockPreimage(event))
+      .accountsPartial({ user: admin })
+      .rpc();
+    const before = await fetchState();
+    await program.methods
+      .refundLock(lockId, admin, event.data.amount)
+      .accountsPartial({ user: admin, admin })
+      .rpc();
+    const after = await fetchState();
+    expect(after.totalReleased.sub(before.totalReleased).eq(event.data.amount)).to.equal(true);
+  });
+
+  // Closes the bridge, so it has to stay the last test.
+  it("Closes a paused bridge only once nothing is outstanding", async () => {
+    await program.methods.setPaused(true).accountsPartial({ admin }).rpc();
+    await expectFailure(
+      program.methods.closeBridge().accountsPartial({ admin }).rpc(),
+      /OutstandingLocks/
+    );
+
+    // Release everything still outstanding.
+    await program.methods.setPaused(false).accountsPartial({ admin }).rpc();
+    const state = await fetchState();
+    const outstanding = state.totalLocked.sub(state.totalReleased);
+    const recipient = Keypair.generate().publicKey;
+    await program.methods
+      .releaseSol(outstanding, recipient, Array.from(Keypair.generate().publicKey.toBytes()))
+      .accountsPartial({ recipient, admin })
+      .rpc();
+
+    await expectFailure(
+      program.methods.closeBridge().accountsPartial({ admin }).rpc(),
+      /BridgeNotPaused/
+    );
+    await program.methods.setPaused(true).accountsPartial({ admin }).rpc();
+    await program.methods.closeBridge().accountsPartial({ admin }).rpc();
+
+    expect(await provider.connection.getAccountInfo(bridgeStatePda)).to.equal(null);
+  });
 });